@@ -0,0 +1,113 @@
+//! Benchmark for loading CCRS crashes, scaled up from the tests/data fixtures
+//!
+//! Run with `cargo bench --bench load`, the number of crashes can be set with `BENCH_ROWS`
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use rusqlite::Connection;
+use switrs_db::schema::{LoadOptions, NewDB, OnConflict, RowFilter};
+
+const FIXTURE: &str = "tests/data/ccrs/Crashes_2024.csv";
+const DEFAULT_ROWS: usize = 20_000;
+
+/// Write a Crashes CSV with `rows` crashes, made from copies of the fixture crashes with new collision ids,
+///   like the statewide data most of the copies are moved out of Berkeley
+fn scaled_crashes(rows: usize) -> PathBuf {
+    let fixture = fs::read_to_string(FIXTURE).expect("failed to read fixture");
+    let mut lines = fixture.lines();
+    let header = lines.next().expect("fixture has no header");
+    let crashes = lines
+        .map(|l| l.split_once(',').expect("no collision id").1)
+        .collect::<Vec<_>>();
+
+    let mut csv = String::from(header);
+    csv.push('\n');
+    for (collision_id, crash) in (0..rows).zip(crashes.iter().cycle()) {
+        let crash = if collision_id % 50 < crashes.len() {
+            crash.to_string()
+        } else {
+            crash.replace(",Berkeley,", ",Sacramento,")
+        };
+        csv.push_str(&format!("{collision_id},{crash}\n"));
+    }
+
+    let path = env::temp_dir().join(format!("switrs-db-bench-{rows}.csv"));
+    fs::write(&path, csv).expect("failed to write scaled crashes");
+    path
+}
+
+/// Load the crashes into a new DB, in memory or in a file
+fn load(data: &Path, db_file: Option<&Path>, options: &LoadOptions<'_>) -> (Duration, usize) {
+    let connection = match db_file {
+        Some(db_file) => {
+            let _ = fs::remove_file(db_file);
+            Connection::open(db_file).expect("failed to open DB")
+        }
+        None => Connection::open_in_memory().expect("failed to open DB"),
+    };
+    connection
+        .create_table("ccrs_crashes", "", Path::new("schema/ccrs_crashes.sql"))
+        .expect("failed to create table");
+
+    let start = Instant::now();
+    let stats = connection
+        .load_data_with_options("ccrs_crashes", data, options)
+        .expect("failed to load crashes");
+
+    (start.elapsed(), stats.rows)
+}
+
+fn main() {
+    let rows = env::var("BENCH_ROWS")
+        .ok()
+        .and_then(|r| r.parse().ok())
+        .unwrap_or(DEFAULT_ROWS);
+    let data = scaled_crashes(rows);
+    let db_file = env::temp_dir().join("switrs-db-bench.sqlite");
+    let berkeley = RowFilter::new("city_name", [String::from("Berkeley")]);
+
+    println!("loading {rows} crashes from {}", data.display());
+    for (name, db_file, batch_size, filter) in [
+        ("memory, 1 per transaction", None, 1, None),
+        ("memory, 10000 per transaction", None, 10_000, None),
+        (
+            "memory, 10000 per transaction, Berkeley",
+            None,
+            10_000,
+            Some(&berkeley),
+        ),
+        ("file, 1 per transaction", Some(db_file.as_path()), 1, None),
+        (
+            "file, 10000 per transaction",
+            Some(db_file.as_path()),
+            10_000,
+            None,
+        ),
+        (
+            "file, 10000 per transaction, Berkeley",
+            Some(db_file.as_path()),
+            10_000,
+            Some(&berkeley),
+        ),
+    ] {
+        let options = LoadOptions {
+            on_conflict: OnConflict::Upsert,
+            batch_size,
+            filter,
+            ..LoadOptions::default()
+        };
+
+        let (elapsed, loaded) = load(&data, db_file, &options);
+        println!(
+            "{name:>40}: {elapsed:>10.3?}, {per_row:>8.2?}/row, {loaded} loaded",
+            per_row = elapsed / rows as u32
+        );
+    }
+
+    let _ = fs::remove_file(&db_file);
+    let _ = fs::remove_file(&data);
+}
@@ -0,0 +1,7 @@
+//! Rebuild when the files embedded in the binary change, see src/resources.rs
+
+fn main() {
+    for path in ["Schemas.toml", "schema", "lookup-tables", "berkeley-tables"] {
+        println!("cargo:rerun-if-changed={path}");
+    }
+}
@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS build_manifest (
+    id INTEGER PRIMARY KEY, -- increments with each build or update of the DB
+    command VARCHAR2 (10), -- build or update
+    tool_version VARCHAR2 (20), -- version of switrs-db that wrote the DB
+    schema_sha256 CHAR(64), -- SHA-256 of the Schemas TOML used
+    ccrs_data_path TEXT, -- the directory the CCRS data was loaded from
+    built_at TEXT -- UTC time of the build, YYYY-MM-DDTHH:MM:SS
+);
+
+CREATE TABLE IF NOT EXISTS build_manifest_files (
+    build_id INTEGER, -- the build_manifest id of the build or update that loaded the file
+    table_name VARCHAR2 (50), -- the table the file was loaded into
+    path TEXT, -- path of the file as it was loaded
+    file_size INTEGER, -- size of the file in bytes
+    sha256 CHAR(64), -- SHA-256 of the file
+    row_count INTEGER, -- records inserted or updated in the table
+    skipped_count INTEGER, -- records not written, because of duplicates or older report versions
+    errored_count INTEGER, -- records that failed to parse
+    filtered_count INTEGER, -- records outside of the jurisdiction, which were not loaded
+    FOREIGN KEY (build_id) REFERENCES build_manifest (id)
+);
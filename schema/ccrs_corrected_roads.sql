@@ -0,0 +1,6 @@
+CREATE TABLE ccrs_corrected_roads (
+    collision_id INTEGER, -- matches the collision_id in ccrs_crashes
+    primary_rd VARCHAR2 (50), -- Primary Road
+    secondary_rd VARCHAR2 (50), -- Secondary Road
+    PRIMARY KEY (collision_id)
+)
@@ -77,6 +77,7 @@ CREATE TABLE IF NOT EXISTS ccrs_crashes (
     evidence_number VARCHAR2 (25),
     is_location_refer_to_narrative CHAR(1), -- True/False
     is_aoi_one_same_as_location CHAR(1), -- True/False
+    ccrs_release VARCHAR2 (25), -- release of the CCRS file the row was loaded from, e.g. 2024 for Crashes_2024.csv
     PRIMARY KEY (collision_id)
 );
 
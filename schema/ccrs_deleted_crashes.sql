@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS ccrs_deleted_crashes (
+    collision_id INTEGER, -- the unique identifier of the crash CCRS marked as deleted
+    report_version INTEGER, -- version of the report that marked the crash as deleted
+    deleted_at TEXT, -- UTC time the crash was deleted, YYYY-MM-DDTHH:MM:SS
+    PRIMARY KEY (collision_id)
+);
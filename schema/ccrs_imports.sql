@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS ccrs_imports (
+    table_name VARCHAR2 (50), -- the table the file was loaded into, e.g. ccrs_crashes
+    file_name VARCHAR2 (100), -- name of the CCRS csv file, e.g. Crashes_2024.csv
+    file_size INTEGER, -- size of the file in bytes when it was imported
+    file_modified INTEGER, -- modification time of the file when it was imported, seconds since the unix epoch
+    row_count INTEGER, -- number of rows read from the file
+    imported_at TEXT, -- UTC time of the import, YYYY-MM-DDTHH:MM:SS
+    PRIMARY KEY (table_name, file_name)
+);
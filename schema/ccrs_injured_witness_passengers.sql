@@ -20,5 +20,37 @@ CREATE TABLE IF NOT EXISTS ccrs_injured_witness_passengers (
     is_vovc_notified TEXT, -- True/False
     party_number SMALLINT,
     seat_position_description VARCHAR2 (50),
+    ccrs_release VARCHAR2 (25), -- release of the CCRS file the row was loaded from, e.g. 2024 for InjuredWitnessPassengers_2024.csv
     PRIMARY KEY (collision_id, injured_wit_pass_id)
-)
+);
+
+CREATE VIEW IF NOT EXISTS ccrs_injured_witness_passengers_view (
+    collision_id,
+    injured_wit_pass_id,
+    party_number,
+    stated_age,
+    gender,
+    is_witness_only,
+    is_passenger_only,
+    injured_person_type,
+    -- joined table names
+    extent_of_injury_name,
+    seat_position_name
+) AS
+SELECT
+    i.collision_id,
+    i.injured_wit_pass_id,
+    i.party_number,
+    i.stated_age,
+    i.gender,
+    i.is_witness_only,
+    i.is_passenger_only,
+    i.injured_person_type,
+    -- joined table names
+    ccrs_extent_of_injury.name,
+    ccrs_seat_position.name
+FROM
+    ccrs_injured_witness_passengers AS i
+    -- join all the lookup tables, CCRS codes are not consistently cased
+    LEFT JOIN ccrs_extent_of_injury ON i.extent_of_injury_code = ccrs_extent_of_injury.id COLLATE NOCASE
+    LEFT JOIN ccrs_seat_position ON i.seat_position = ccrs_seat_position.id COLLATE NOCASE;
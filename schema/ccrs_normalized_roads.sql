@@ -1,4 +1,4 @@
-CREATE TABLE ccrs_normalized_roads (
+CREATE TABLE IF NOT EXISTS ccrs_normalized_roads (
     collision_id VARCHAR2 (19), -- matches the case_id in collisions
     primary_rd VARCHAR2 (50), -- Primary Road
     primary_rd_address VARCHAR2 (10), -- address if one exists on the road where the collision occured
@@ -37,5 +37,45 @@ CREATE TABLE IF NOT EXISTS ccrs_parties (
     thru_lanes TEXT,
     total_lanes TEXT,
     is_dre_conducted TEXT, -- True/False
+    ccrs_release VARCHAR2 (25), -- release of the CCRS file the row was loaded from, e.g. 2024 for Parties_2024.csv
     PRIMARY KEY (party_id, collision_id)
-)
+);
+
+CREATE VIEW IF NOT EXISTS ccrs_parties_view (
+    party_id,
+    collision_id,
+    party_number,
+    party_type,
+    is_at_fault,
+    is_hit_and_run,
+    stated_age,
+    speed_limit,
+    street_or_highway_name,
+    -- joined table names
+    party_type_name,
+    movement_prec_coll_name,
+    sobriety_drug_physical_1_name,
+    sobriety_drug_physical_2_name
+) AS
+SELECT
+    p.party_id,
+    p.collision_id,
+    p.party_number,
+    p.party_type,
+    p.is_at_fault,
+    p.is_hit_and_run,
+    p.stated_age,
+    p.speed_limit,
+    p.street_or_highway_name,
+    -- joined table names
+    ccrs_party_type.name,
+    ccrs_movement_prec_coll.name,
+    sobriety_drug_physical_1.name,
+    sobriety_drug_physical_2.name
+FROM
+    ccrs_parties AS p
+    -- join all the lookup tables, CCRS codes are not consistently cased
+    LEFT JOIN ccrs_party_type ON p.party_type = ccrs_party_type.id COLLATE NOCASE
+    LEFT JOIN ccrs_movement_prec_coll ON p.movement_prec_coll_code = ccrs_movement_prec_coll.id COLLATE NOCASE
+    LEFT JOIN ccrs_sobriety_drug_physical sobriety_drug_physical_1 ON p.sobriety_drug_physical_code1 = sobriety_drug_physical_1.id COLLATE NOCASE
+    LEFT JOIN ccrs_sobriety_drug_physical sobriety_drug_physical_2 ON p.sobriety_drug_physical_code2 = sobriety_drug_physical_2.id COLLATE NOCASE;
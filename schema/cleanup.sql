@@ -1,7 +1,7 @@
--- clean up non-berkeley stuff from ccrs
+-- clean up anything outside of the jurisdiction from ccrs (see [jurisdiction] in Schemas.toml)
 DELETE FROM ccrs_crashes
 WHERE
-    city_name <> 'Berkeley';
+    NOT coalesce({ccrs_jurisdiction}, 0);
 
 -- delete any parties without collisions
 DELETE FROM ccrs_parties
@@ -0,0 +1,142 @@
+-- harmonized view of the crashes from both the legacy SWITRS data and the newer CCRS data
+--   crashes reported in both sources are only listed once, from CCRS
+CREATE VIEW IF NOT EXISTS crashes_view (
+    source,
+    crash_id,
+    report_number,
+    crash_datetime,
+    city_name,
+    primary_rd,
+    secondary_rd,
+    corrected_primary_rd,
+    corrected_secondary_rd,
+    severity,
+    type_of_collision_name,
+    number_killed,
+    number_injured,
+    pedestrian_accident,
+    bicycle_accident,
+    latitude,
+    longitude
+) AS
+SELECT
+    'ccrs',
+    CAST(c.collision_id AS TEXT),
+    c.report_number,
+    strftime ('%Y-%m-%dT%H:%M', c.crash_date_time),
+    c.city_name,
+    c.primary_road,
+    c.secondary_road,
+    ccrs_corrected_roads.primary_rd,
+    ccrs_corrected_roads.secondary_rd,
+    CASE
+        WHEN c.number_killed > 0 THEN 'Fatal'
+        WHEN c.number_injured > 0 THEN 'Injury'
+        ELSE 'Property Damage Only'
+    END,
+    type_of_collision.name,
+    c.number_killed,
+    c.number_injured,
+    iif (
+        upper(c.motor_vehicle_involved_with_desc) = 'PEDESTRIAN'
+        OR EXISTS (
+            SELECT
+                1
+            FROM
+                ccrs_parties AS p
+            WHERE
+                p.collision_id = c.collision_id
+                AND upper(p.party_type) = 'PEDESTRIAN'
+        ),
+        'Y',
+        NULL
+    ),
+    iif (
+        upper(c.motor_vehicle_involved_with_desc) = 'BICYCLE'
+        OR EXISTS (
+            SELECT
+                1
+            FROM
+                ccrs_parties AS p
+            WHERE
+                p.collision_id = c.collision_id
+                AND upper(p.party_type) = 'BICYCLIST'
+        ),
+        'Y',
+        NULL
+    ),
+    c.latitude,
+    c.longitude
+FROM
+    ccrs_crashes AS c
+    LEFT JOIN ccrs_corrected_roads ON c.collision_id = ccrs_corrected_roads.collision_id
+    LEFT JOIN type_of_collision ON c.collision_type_code = type_of_collision.id
+UNION ALL
+SELECT
+    'switrs',
+    s.case_id,
+    raw.local_report_number,
+    s.collision_datetime,
+    s.city_name,
+    s.primary_rd,
+    s.secondary_rd,
+    s.corrected_primary_rd,
+    s.corrected_secondary_rd,
+    -- see lookup-tables/COLLISION_SEVERITY.csv, unknown codes are left NULL
+    CASE raw.collision_severity
+        WHEN '1' THEN 'Fatal'
+        WHEN '2' THEN 'Injury'
+        WHEN '3' THEN 'Injury'
+        WHEN '4' THEN 'Injury'
+        WHEN '0' THEN 'Property Damage Only'
+    END,
+    s.type_of_collision_name,
+    s.number_killed,
+    s.number_injured,
+    s.pedestrian_accident,
+    s.bicycle_accident,
+    s.latitude,
+    s.longitude
+FROM
+    switrs_collisions_view AS s
+    LEFT JOIN switrs_collisions AS raw ON s.case_id = raw.case_id
+WHERE
+    -- skip any SWITRS crash that was also reported to CCRS, matched on the police report number of the same agency,
+    --   the report numbers are only unique within one NCIC, or on the time of the crash and the corrected roads,
+    --   each match has its own index, see below
+    NOT EXISTS (
+        SELECT
+            1
+        FROM
+            ccrs_crashes AS c
+        WHERE
+            c.report_number = raw.local_report_number
+            AND CAST(c.ncic_code AS INTEGER) = raw.juris
+    )
+    AND NOT EXISTS (
+        SELECT
+            1
+        FROM
+            ccrs_crashes AS c
+            JOIN ccrs_corrected_roads AS cr ON c.collision_id = cr.collision_id
+        WHERE
+            strftime ('%Y-%m-%dT%H:%M', c.crash_date_time) = s.collision_datetime
+            AND (
+                (
+                    cr.primary_rd = s.corrected_primary_rd
+                    AND cr.secondary_rd = s.corrected_secondary_rd
+                )
+                OR (
+                    cr.primary_rd = s.corrected_secondary_rd
+                    AND cr.secondary_rd = s.corrected_primary_rd
+                )
+            )
+    );
+
+-- the CCRS crashes matched by the SWITRS crashes, and the parties of each CCRS crash, without these every crash in
+--   crashes_view scans all of them
+CREATE INDEX IF NOT EXISTS idx_ccrs_crashes_report_number ON ccrs_crashes (report_number, ncic_code);
+
+CREATE INDEX IF NOT EXISTS idx_ccrs_crashes_crash_minute ON ccrs_crashes (strftime ('%Y-%m-%dT%H:%M', crash_date_time));
+
+CREATE INDEX IF NOT EXISTS idx_ccrs_parties_collision_id ON ccrs_parties (collision_id);
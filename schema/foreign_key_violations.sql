@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS foreign_key_violations (
+    table_name VARCHAR2 (50), -- the table with the rows, e.g. switrs_collisions
+    column_name VARCHAR2 (100), -- the columns of the FOREIGN KEY, separated by commas, e.g. weather_1
+    parent_table VARCHAR2 (50), -- the table the FOREIGN KEY references, e.g. weather
+    code TEXT, -- the code that isn't in the referenced table, the values of the columns separated by commas
+    row_count INTEGER, -- number of rows with the code
+    PRIMARY KEY (table_name, column_name, code)
+);
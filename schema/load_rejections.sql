@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS load_rejections (
+    build_id INTEGER, -- the build_manifest id of the build or update that rejected the record
+    table_name VARCHAR2 (50), -- the table the record was to be loaded into
+    path TEXT, -- path of the file with the record
+    line_number INTEGER, -- line of the record in the file
+    record TEXT, -- the record as it was in the file, NULL if it couldn't be parsed
+    error_kind VARCHAR2 (10), -- parse, duplicate or constraint
+    error_message TEXT, -- the error from parsing or inserting the record
+    FOREIGN KEY (build_id) REFERENCES build_manifest (id)
+);
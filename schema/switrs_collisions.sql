@@ -248,7 +248,7 @@ FROM
     LEFT JOIN ramp secondary_ramp ON c.secondary_ramp = secondary_ramp.id
     LEFT JOIN switrs_corrected_roads ON c.case_id = switrs_corrected_roads.case_id
 WHERE
-    c.cnty_city_loc IN ("0102", "0103") -- see lookup-tables/CNTY_CITY_LOC.csv
+    {switrs_jurisdiction} -- see [jurisdiction] in Schemas.toml and lookup-tables/CNTY_CITY_LOC.csv
 ;
 
 -- view of the data included in this DB
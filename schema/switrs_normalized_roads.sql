@@ -1,4 +1,4 @@
-CREATE TABLE switrs_normalized_roads (
+CREATE TABLE IF NOT EXISTS switrs_normalized_roads (
     case_id VARCHAR2 (19), -- matches the case_id in collisions
     primary_rd VARCHAR2 (50), -- Primary Road
     primary_rd_address VARCHAR2 (10), -- address if one exists on the road where the collision occured
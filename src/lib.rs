@@ -1,3 +1,10 @@
 //! SWITRS DB builder library
 
+pub mod report;
+pub mod resources;
+pub mod review;
+pub mod road;
 pub mod schema;
+
+#[cfg(test)]
+mod test_support;
@@ -1,58 +1,523 @@
 //! CLI for generating the Sqlite DB from the SWITRS database
 
-use std::path::{Path, PathBuf};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use log::info;
-use rusqlite::{Connection, DatabaseName};
+use rusqlite::{Connection, DatabaseName, OpenFlags};
 
-use switrs_db::schema::{NewDB, Schema};
+use switrs_db::{
+    report::{self, Report},
+    review,
+    schema::{Corrections, Jurisdiction, NewDB, Schema},
+};
 
+/// Schemas TOML used if there is one in the current directory
+const SCHEMAS_TOML: &str = "Schemas.toml";
+
+/// Directory with the legacy SWITRS data, relative to the Schemas TOML
 const OLD_SWITRS_PATH: &str = "old-switrs";
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Build a new SQLITE db from the raw data
+    Build {
+        #[command(flatten)]
+        data: DataArgs,
+
+        /// SQLITE db file to create from the raw data
+        #[arg(short = 'f')]
+        sqlite_file: PathBuf,
+
+        /// Where the DB is built before it is written to the SQLITE db file
+        #[arg(long = "build-mode", value_enum, default_value_t = BuildMode::Memory)]
+        build_mode: BuildMode,
+    },
+    /// Add the CCRS data to an existing SQLITE db
+    Update {
+        #[command(flatten)]
+        data: DataArgs,
+
+        /// SQLITE db file, previously created with build, to update
+        #[arg(short = 'f')]
+        sqlite_file: PathBuf,
+    },
+    /// Check the Schemas TOML, and load all the raw data without writing a SQLITE db, to check for errors
+    Validate {
+        #[command(flatten)]
+        data: DataArgs,
+
+        /// Only check the Schemas TOML and that the files it uses exist, without loading any data
+        #[arg(long = "schema-only")]
+        schema_only: bool,
+    },
+    /// Run a canned analysis on a SQLITE db
+    Report {
+        /// SQLITE db file, previously created with build
+        #[arg(short = 'f')]
+        sqlite_file: PathBuf,
+
+        /// The report to run
+        report: Report,
+    },
+    /// Write a table or view from a SQLITE db to a CSV file
+    Export {
+        /// SQLITE db file, previously created with build
+        #[arg(short = 'f')]
+        sqlite_file: PathBuf,
+
+        /// Name of the table or view to export, e.g. crashes_view
+        #[arg(short = 't')]
+        table: String,
+
+        /// CSV file to write, stdout if not specified
+        #[arg(short = 'o')]
+        output: Option<PathBuf>,
+    },
+    /// Review the roads a SQLITE db couldn't correct, and add the decisions to the road typos and corrected roads
+    ///   lookup tables of the Schemas TOML
+    Review {
+        /// SQLITE db file, previously created with build
+        #[arg(short = 'f')]
+        sqlite_file: PathBuf,
+
+        /// Path to the Schemas TOML configuration file, the Schemas.toml in the current directory if not specified
+        #[arg(short = 's')]
+        schema: Option<PathBuf>,
+    },
+}
+
+/// Where a new DB is built
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+enum BuildMode {
+    /// Build the DB in memory and then write it to the file, fastest but all the data must fit in RAM
+    Memory,
+    /// Build the DB in a temporary file next to the SQLITE db file, which is then renamed to it
+    Disk,
+}
+
+/// Page cache of the DB when building on disk, in KiB
+const DISK_CACHE_SIZE_KIB: i64 = 64 * 1024;
+
+/// Options for loading the raw data
+#[derive(Args, Debug)]
+struct DataArgs {
     /// Path to the raw data dump from CCRS, ckan, Open Data Portal for California
     #[arg(short = 'd')]
     data_path: PathBuf,
 
-    /// SQLITE db file to create from the raw data
-    #[arg(short = 'f')]
-    sqlite_file: PathBuf,
+    /// Path to the Schemas TOML configuration file, if not specified the Schemas.toml in the current directory,
+    ///   or the one embedded in the binary
+    #[arg(short = 's')]
+    schema: Option<PathBuf>,
+
+    /// Directory with the legacy SWITRS data, the old-switrs directory next to the Schemas TOML if not specified
+    #[arg(long = "old-switrs")]
+    old_switrs: Option<PathBuf>,
+
+    /// City name or cnty_city_loc code to build the DB for, replaces the [jurisdiction] in the Schemas TOML
+    #[arg(short = 'j', long = "jurisdiction")]
+    jurisdiction: Vec<String>,
+
+    /// County name to build the DB for, replaces the [jurisdiction] in the Schemas TOML
+    #[arg(long = "county")]
+    counties: Vec<String>,
+
+    /// Number of records loaded in each transaction, replaces the batch-size in the Schemas TOML
+    #[arg(long = "batch-size")]
+    batch_size: Option<usize>,
+
+    /// Number of threads parsing the CCRS files while they are loaded, replaces the parse-workers in the Schemas TOML
+    #[arg(long = "parse-workers")]
+    parse_workers: Option<usize>,
+
+    /// Directory to write the corrected roads and road suggestions to, replaces their directories in the Schemas TOML
+    #[arg(long = "corrected-roads-dir")]
+    corrected_roads_dir: Option<PathBuf>,
+
+    /// File to write the ranked suggestions for the roads that couldn't be corrected to, replaces the road-suggestions
+    ///   in the Schemas TOML, no suggestions are written if neither is set
+    #[arg(long = "road-suggestions")]
+    road_suggestions: Option<PathBuf>,
 
-    /// Path to the Schemas TOML configuration file
-    #[arg(short = 's', default_value = "Schemas.toml")]
-    schema: PathBuf,
+    /// Write the corrected roads, only check them against the corrected roads lookup tables, or skip both
+    #[arg(long = "corrections", value_enum, default_value_t = Corrections::Write)]
+    corrections: Corrections,
+
+    /// Fail if any rows have codes that aren't in the tables their FOREIGN KEYs reference, e.g. the lookup tables,
+    ///   otherwise they are only listed in the foreign_key_violations table
+    #[arg(long = "strict-foreign-keys")]
+    strict_foreign_keys: bool,
+}
+
+impl DataArgs {
+    /// Load the Schemas TOML, with the jurisdiction, batch size, parse workers, corrected roads, road suggestions and
+    ///   strictness from the command line
+    fn schemas(&self) -> Result<Schema, Box<dyn std::error::Error>> {
+        let mut schemas = load_schemas(self.schema.as_deref())?;
+        let jurisdiction = Jurisdiction::new(self.jurisdiction.clone(), self.counties.clone());
+        if !jurisdiction.is_empty() {
+            schemas.set_jurisdiction(jurisdiction);
+        }
+        if let Some(batch_size) = self.batch_size {
+            schemas.set_batch_size(batch_size);
+        }
+        if let Some(parse_workers) = self.parse_workers {
+            schemas.set_parse_workers(parse_workers);
+        }
+        if let Some(corrected_roads_dir) = &self.corrected_roads_dir {
+            schemas.set_corrected_roads_dir(corrected_roads_dir);
+        }
+        if let Some(road_suggestions) = &self.road_suggestions {
+            schemas.set_road_suggestions(road_suggestions);
+        }
+        schemas.set_corrections(self.corrections);
+        schemas.set_strict_foreign_keys(self.strict_foreign_keys);
+
+        Ok(schemas)
+    }
+
+    /// Directory with the legacy SWITRS data
+    fn old_switrs_path(&self, schemas: &Schema) -> PathBuf {
+        self.old_switrs
+            .clone()
+            .unwrap_or_else(|| schemas.resolve_path(Path::new(OLD_SWITRS_PATH)))
+    }
+}
+
+/// Load the Schemas TOML, the one in the current directory or the embedded one if there is no path
+fn load_schemas(schema: Option<&Path>) -> Result<Schema, Box<dyn std::error::Error>> {
+    Ok(match schema {
+        Some(schema) => Schema::from_toml_file(schema)?,
+        None if Path::new(SCHEMAS_TOML).is_file() => {
+            Schema::from_toml_file(Path::new(SCHEMAS_TOML))?
+        }
+        None => {
+            info!("No {SCHEMAS_TOML} in the current directory, using the embedded one");
+            Schema::embedded()?
+        }
+    })
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+fn main() -> ExitCode {
+    let cli = Cli::parse();
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("switrs_db=info"))
         .init();
 
-    let old_switrs_path = Path::new(OLD_SWITRS_PATH);
-    let data_path = args.data_path;
-    let sqlite_file = args.sqlite_file;
-    let schema = args.schema;
+    // print the errors with Display, the SchemaError problems are listed one per line
+    match run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run the command
+fn run(command: Command) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Build {
+            data,
+            sqlite_file,
+            build_mode,
+        } => build(&data, &sqlite_file, build_mode),
+        Command::Update { data, sqlite_file } => update(&data, &sqlite_file),
+        Command::Validate { data, schema_only } => validate(&data, schema_only),
+        Command::Report {
+            sqlite_file,
+            report,
+        } => {
+            let connection = open_existing(&sqlite_file, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            report::write_report(&connection, report, &mut io::stdout().lock())
+        }
+        Command::Export {
+            sqlite_file,
+            table,
+            output,
+        } => {
+            let connection = open_existing(&sqlite_file, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            match output {
+                Some(output) => report::export_csv(&connection, &table, fs::File::create(output)?)?,
+                None => report::export_csv(&connection, &table, io::stdout().lock())?,
+            };
+            Ok(())
+        }
+        Command::Review {
+            sqlite_file,
+            schema,
+        } => {
+            let connection = open_existing(&sqlite_file, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            let schemas = load_schemas(schema.as_deref())?;
+            let summary = review::review_roads(
+                &connection,
+                &schemas,
+                &mut io::stdin().lock(),
+                &mut io::stdout().lock(),
+            )?;
+            info!(
+                "Added {typos} road typos and corrected the roads of {corrected} crashes, rebuild the DB to use them",
+                typos = summary.typos,
+                corrected = summary.corrected
+            );
+            Ok(())
+        }
+    }
+}
 
+/// Build the DB in memory or on disk, in a temporary file that replaces the SQLITE db file once it has been checked,
+///   any previous DB is kept as a .bak
+fn build(
+    data: &DataArgs,
+    sqlite_file: &Path,
+    build_mode: BuildMode,
+) -> Result<(), Box<dyn std::error::Error>> {
     info!(
         "Loading data from {data_path} and writing to {sqlite_file}",
-        data_path = data_path.display(),
+        data_path = data.data_path.display(),
         sqlite_file = sqlite_file.display()
     );
 
-    // we'll build the DB in memory, and then store in a file
+    // in the same directory, so the rename is atomic
+    let temp_file = sibling_file(sqlite_file, "tmp");
+    if temp_file.exists() {
+        fs::remove_file(&temp_file)?;
+    }
+
+    let schemas = data.schemas()?;
+    let built = match build_mode {
+        BuildMode::Memory => build_in_memory(&schemas, data, &temp_file),
+        BuildMode::Disk => build_on_disk(&schemas, data, &temp_file),
+    }
+    .and_then(|()| check_db_file(&temp_file));
+    if let Err(e) = built {
+        let _ = fs::remove_file(&temp_file);
+        return Err(e);
+    }
+
+    info!(
+        "Successfully built DB, moving it to {sqlite_file}",
+        sqlite_file = sqlite_file.display()
+    );
+    replace_db_file(&temp_file, sqlite_file)
+}
+
+/// Replace the SQLITE db file with the checked temp_file, any previous DB is kept as a .bak
+///
+/// The previous DB stays in place until the temp_file is renamed over it, the .bak is a hard link to it, or a copy
+///   where links aren't supported, so there is always a DB at the path, and the rename is synced to the disk.
+fn replace_db_file(temp_file: &Path, sqlite_file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if sqlite_file.exists() {
+        let backup = sibling_file(sqlite_file, "bak");
+        info!(
+            "Keeping the previous DB as {backup}",
+            backup = backup.display()
+        );
+        if backup.exists() {
+            fs::remove_file(&backup)?;
+        }
+        if fs::hard_link(sqlite_file, &backup).is_err() {
+            fs::copy(sqlite_file, &backup)?;
+        }
+    }
+
+    // the temp_file is written without syncs by the bulk load
+    fs::File::open(temp_file)?.sync_all()?;
+    fs::rename(temp_file, sqlite_file)?;
+    sync_parent_dir(sqlite_file)?;
+    Ok(())
+}
+
+/// Sync the directory of the file, so a rename into it is on the disk
+#[cfg(unix)]
+fn sync_parent_dir(file: &Path) -> std::io::Result<()> {
+    let dir = match file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::File::open(dir)?.sync_all()
+}
+
+/// Directories can't be opened to sync them on other platforms, where the rename is synced by the file system
+#[cfg(not(unix))]
+fn sync_parent_dir(_file: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// The path of the file with the extension added, e.g. switrs.sqlite.tmp
+fn sibling_file(file: &Path, extension: &str) -> PathBuf {
+    let mut sibling = file.as_os_str().to_owned();
+    sibling.push(".");
+    sibling.push(extension);
+    PathBuf::from(sibling)
+}
+
+/// Build the DB in memory, and then write it to the temp_file
+fn build_in_memory(
+    schemas: &Schema,
+    data: &DataArgs,
+    temp_file: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
     let connection = Connection::open_in_memory()?;
+    connection.load_from_schema(schemas, &data.old_switrs_path(schemas), &data.data_path)?;
+
+    info!(
+        "Imported data, writing DB to {temp_file}",
+        temp_file = temp_file.display()
+    );
+    connection.backup(DatabaseName::Main, temp_file, None)?;
+    Ok(())
+}
+
+/// Build the DB in a new file, with pragmas for a faster bulk load
+///
+/// Nothing needs to survive a crash, the file is discarded, so the data isn't synced to disk while loading
+///   and the rollback journal is kept in memory, it only holds the current batch.
+fn build_on_disk(
+    schemas: &Schema,
+    data: &DataArgs,
+    temp_file: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!(
+        "Building DB in {temp_file}",
+        temp_file = temp_file.display()
+    );
+    let connection = open_for_bulk_load(temp_file)?;
+    connection.load_from_schema(schemas, &data.old_switrs_path(schemas), &data.data_path)?;
+    close_bulk_load(connection)
+}
+
+/// Open the temporary DB file with pragmas for a faster bulk load
+fn open_for_bulk_load(temp_file: &Path) -> Result<Connection, Box<dyn std::error::Error>> {
+    let connection = Connection::open(temp_file)?;
+    connection.pragma_update(None, "journal_mode", "MEMORY")?;
+    connection.pragma_update(None, "synchronous", "OFF")?;
+    connection.pragma_update(None, "cache_size", -DISK_CACHE_SIZE_KIB)?;
+    Ok(connection)
+}
+
+/// Close the temporary DB file after the bulk load, with the journal mode back to the default for the users of the DB
+fn close_bulk_load(connection: Connection) -> Result<(), Box<dyn std::error::Error>> {
+    connection.pragma_update(None, "journal_mode", "DELETE")?;
+    connection.close().map_err(|(_, e)| e)?;
+    Ok(())
+}
+
+/// Check the DB written to the file, and make sure it is on disk before it replaces the previous DB
+fn check_db_file(sqlite_file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    info!(
+        "Checking DB in {sqlite_file}",
+        sqlite_file = sqlite_file.display()
+    );
+    let connection = open_existing(sqlite_file, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    connection.check_build()?;
+    connection.close().map_err(|(_, e)| e)?;
 
-    let schemas = Schema::from_toml_file(&schema)?;
-    connection.load_from_schema(&schemas, &old_switrs_path, &data_path)?;
+    fs::File::open(sqlite_file)?.sync_all()?;
+    Ok(())
+}
+
+/// Load the CCRS data into a copy of the existing DB file, the copy replaces the DB once it has been checked,
+///   so a failed update leaves the DB as it was, and the previous DB is kept as a .bak
+fn update(data: &DataArgs, sqlite_file: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    info!(
+        "Loading data from {data_path} and updating {sqlite_file}",
+        data_path = data.data_path.display(),
+        sqlite_file = sqlite_file.display()
+    );
+
+    let temp_file = sibling_file(sqlite_file, "tmp");
+    if temp_file.exists() {
+        fs::remove_file(&temp_file)?;
+    }
+
+    let schemas = data.schemas()?;
+    let updated = update_copy(&schemas, data, sqlite_file, &temp_file)
+        .and_then(|()| check_db_file(&temp_file));
+    if let Err(e) = updated {
+        let _ = fs::remove_file(&temp_file);
+        return Err(e);
+    }
 
     info!(
-        "Successfully imported data, writing DB to {sqlite_file}",
+        "Successfully updated DB, moving it to {sqlite_file}",
         sqlite_file = sqlite_file.display()
     );
-    connection.backup(DatabaseName::Main, sqlite_file, None)?;
+    replace_db_file(&temp_file, sqlite_file)
+}
+
+/// Copy the existing DB file to the temp_file, and load the CCRS data into the copy
+fn update_copy(
+    schemas: &Schema,
+    data: &DataArgs,
+    sqlite_file: &Path,
+    temp_file: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let existing = open_existing(sqlite_file, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    info!(
+        "Copying {sqlite_file} to {temp_file}",
+        sqlite_file = sqlite_file.display(),
+        temp_file = temp_file.display()
+    );
+    existing.backup(DatabaseName::Main, temp_file, None)?;
+    existing.close().map_err(|(_, e)| e)?;
 
+    let connection = open_for_bulk_load(temp_file)?;
+    connection.update_from_schema(schemas, &data.data_path)?;
+    close_bulk_load(connection)
+}
+
+/// Check the Schemas TOML, and then load all the data into an in memory DB, which is then discarded
+fn validate(data: &DataArgs, schema_only: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let mut schemas = data.schemas()?;
+    // validating never writes any files, the corrected roads are only compared with --corrections check
+    if data.corrections != Corrections::Check {
+        schemas.set_corrections(Corrections::Skip);
+    }
+    if schema_only {
+        schemas.validate(Some(&data.old_switrs_path(&schemas)), &data.data_path)?;
+        info!("Successfully validated the Schemas TOML");
+        return Ok(());
+    }
+
+    info!(
+        "Validating data from {data_path}",
+        data_path = data.data_path.display()
+    );
+
+    let connection = Connection::open_in_memory()?;
+    connection.load_from_schema(&schemas, &data.old_switrs_path(&schemas), &data.data_path)?;
+
+    info!("Successfully validated data");
     Ok(())
 }
+
+/// Open a DB that was previously created with build, without creating a new file
+fn open_existing(
+    sqlite_file: &Path,
+    flags: OpenFlags,
+) -> Result<Connection, Box<dyn std::error::Error>> {
+    if !sqlite_file.is_file() {
+        return Err(format!(
+            "{sqlite_file} does not exist, create it with build",
+            sqlite_file = sqlite_file.display()
+        )
+        .into());
+    }
+
+    Ok(Connection::open_with_flags(
+        sqlite_file,
+        flags - OpenFlags::SQLITE_OPEN_CREATE,
+    )?)
+}
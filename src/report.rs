@@ -0,0 +1,206 @@
+//! Canned reports and exports from a generated SWITRS sqlite DB
+
+use std::io::Write;
+
+use log::info;
+use rusqlite::{types::ValueRef, Connection, Statement};
+
+/// Canned analyses of the generated DB
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum Report {
+    /// First and last dates of the SWITRS and CCRS data in the DB
+    Version,
+    /// Age cohorts of pedestrian victims in the SWITRS data
+    VictimCohort,
+    /// Age cohorts of drivers in the SWITRS data
+    PartyCohort,
+    /// Tool version, Schemas TOML hash and files loaded by the last build or update
+    Manifest,
+}
+
+impl Report {
+    /// The queries run for the report, each is printed in order
+    fn queries(self) -> &'static [&'static str] {
+        match self {
+            Self::Version => &[
+                "SELECT * FROM switrs_version_view",
+                "SELECT * FROM ccrs_version_view",
+            ],
+            // victim_role: 1 Driver, 3 Pedestrian, 4 Bicyclist
+            Self::VictimCohort => &["
+                SELECT
+                    SUM(CASE WHEN victim_age < 12 THEN 1 ELSE 0 END) AS 'Under 12',
+                    SUM(CASE WHEN victim_age BETWEEN 12 AND 18 THEN 1 ELSE 0 END) AS '12-18',
+                    SUM(CASE WHEN victim_age BETWEEN 19 AND 25 THEN 1 ELSE 0 END) AS '18-25',
+                    SUM(CASE WHEN victim_age BETWEEN 26 AND 40 THEN 1 ELSE 0 END) AS '26-40',
+                    SUM(CASE WHEN victim_age BETWEEN 41 AND 60 THEN 1 ELSE 0 END) AS '41-60',
+                    SUM(CASE WHEN victim_age BETWEEN 61 AND 75 THEN 1 ELSE 0 END) AS '61-75',
+                    SUM(CASE WHEN victim_age > 75 THEN 1 ELSE 0 END) AS 'Over 75'
+                FROM switrs_victims_view
+                WHERE victim_role_name = 'Pedestrian'
+            "],
+            // party_type: 1 Driver (including Hit and Run), 2 Pedestrian, 4 Bicyclist
+            Self::PartyCohort => &["
+                SELECT
+                    SUM(CASE WHEN party_age < 12 THEN 1 ELSE 0 END) AS 'Under 12',
+                    SUM(CASE WHEN party_age BETWEEN 12 AND 18 THEN 1 ELSE 0 END) AS '12-18',
+                    SUM(CASE WHEN party_age BETWEEN 19 AND 25 THEN 1 ELSE 0 END) AS '18-25',
+                    SUM(CASE WHEN party_age BETWEEN 26 AND 40 THEN 1 ELSE 0 END) AS '26-40',
+                    SUM(CASE WHEN party_age BETWEEN 41 AND 60 THEN 1 ELSE 0 END) AS '41-60',
+                    SUM(CASE WHEN party_age BETWEEN 61 AND 75 THEN 1 ELSE 0 END) AS '61-75',
+                    SUM(CASE WHEN party_age > 75 THEN 1 ELSE 0 END) AS 'Over 75'
+                FROM switrs_parties_view
+                WHERE party_type = '1'
+            "],
+            Self::Manifest => &[
+                "SELECT * FROM build_manifest ORDER BY id DESC LIMIT 1",
+                "SELECT table_name, path, file_size, sha256, row_count, skipped_count, errored_count, filtered_count
+                FROM build_manifest_files
+                WHERE build_id = (SELECT max(id) FROM build_manifest)",
+            ],
+        }
+    }
+}
+
+/// Run the report against the DB and write the results, one `column = value` line per column like `sqlite3 -line`
+pub fn write_report(
+    connection: &Connection,
+    report: Report,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for query in report.queries() {
+        let mut stmt = connection.prepare(query)?;
+        write_lines(&mut stmt, out)?;
+    }
+
+    Ok(())
+}
+
+/// Write all rows of the statement, one `column = value` line per column
+fn write_lines(
+    stmt: &mut Statement<'_>,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let names = stmt
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let width = names.iter().map(|n| n.len()).max().unwrap_or(0);
+
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        for (i, name) in names.iter().enumerate() {
+            writeln!(
+                out,
+                "{name:>width$} = {value}",
+                value = display(row.get_ref(i)?)
+            )?;
+        }
+        writeln!(out)?;
+    }
+
+    Ok(())
+}
+
+/// Display a value from the DB, NULL is empty
+fn display(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned(),
+        ValueRef::Blob(b) => format!("<{} bytes>", b.len()),
+    }
+}
+
+/// Export all rows of the table or view as CSV with a header, returns the number of rows written
+pub fn export_csv(
+    connection: &Connection,
+    name: &str,
+    out: impl Write,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    // only allow known tables and views, the name is used directly in the query
+    let exists: bool = connection.query_row(
+        "SELECT count(*) > 0 FROM sqlite_master WHERE type IN ('table', 'view') AND name = ?",
+        [name],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Err(format!("no such table or view: {name}").into());
+    }
+
+    let mut stmt = connection.prepare(&format!("SELECT * FROM \"{name}\""))?;
+    let mut csv = csv::Writer::from_writer(out);
+    csv.write_record(stmt.column_names())?;
+
+    let column_count = stmt.column_count();
+    let mut count = 0;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let record = (0..column_count)
+            .map(|i| row.get_ref(i).map(display))
+            .collect::<Result<Vec<_>, _>>()?;
+        csv.write_record(&record)?;
+        count += 1;
+    }
+    csv.flush()?;
+
+    info!("EXPORTED {count} rows from {name}");
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        schema::{NewDB, Schema},
+        test_support::create_tables,
+    };
+
+    fn test_db() -> Connection {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        connection
+            .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)
+            .expect("failed to init lookup tables");
+
+        create_tables(
+            &connection,
+            &[
+                ("switrs_collisions", Some("tests/data/collisions.csv")),
+                ("switrs_parties", Some("tests/data/parties.csv")),
+            ],
+        );
+
+        connection
+    }
+
+    #[test]
+    fn test_party_cohort_report() {
+        let connection = test_db();
+
+        let mut out = Vec::new();
+        write_report(&connection, Report::PartyCohort, &mut out).expect("report failed");
+        let out = String::from_utf8(out).expect("not utf8");
+
+        assert!(out.starts_with("Under 12 = 0\n"));
+        assert_eq!(8, out.lines().count());
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let connection = test_db();
+
+        let mut out = Vec::new();
+        let count = export_csv(&connection, "day_of_week", &mut out).expect("export failed");
+        assert_eq!(7, count);
+
+        let out = String::from_utf8(out).expect("not utf8");
+        assert_eq!(Some("id,name"), out.lines().next());
+
+        assert!(export_csv(&connection, "no_such_table", Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! The default Schemas TOML, table schemas and lookup tables, embedded in the binary
+//!
+//! Paths under EMBEDDED_ROOT are read from the embedded files, all other paths from disk, so the same Schema
+//!   works for both.
+
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Component, Path},
+};
+
+use include_dir::{include_dir, Dir};
+
+/// Root of the paths to the embedded files, e.g. `<embedded>/schema/crashes.sql`
+pub const EMBEDDED_ROOT: &str = "<embedded>";
+
+/// The Schemas TOML, its paths are relative to EMBEDDED_ROOT
+pub(crate) const SCHEMAS_TOML: &str = include_str!("../Schemas.toml");
+
+static SCHEMA: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/schema");
+static LOOKUP_TABLES: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/lookup-tables");
+static BERKELEY_TABLES: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/berkeley-tables");
+
+/// True if the path is to an embedded file
+pub fn is_embedded(path: &Path) -> bool {
+    path.starts_with(EMBEDDED_ROOT)
+}
+
+/// The contents of the embedded file at the path
+fn embedded(path: &Path) -> Option<&'static [u8]> {
+    let mut components = path.strip_prefix(EMBEDDED_ROOT).ok()?.components();
+    let dir = match components.next()? {
+        Component::Normal(dir) if dir == "schema" => &SCHEMA,
+        Component::Normal(dir) if dir == "lookup-tables" => &LOOKUP_TABLES,
+        Component::Normal(dir) if dir == "berkeley-tables" => &BERKELEY_TABLES,
+        _ => return None,
+    };
+
+    dir.get_file(components.as_path())
+        .map(|file| file.contents())
+}
+
+/// Open the file for reading, from the embedded files or disk
+pub fn open(path: &Path) -> io::Result<Box<dyn Read + Send>> {
+    if !is_embedded(path) {
+        return Ok(Box::new(fs::File::open(path)?));
+    }
+
+    match embedded(path) {
+        Some(contents) => Ok(Box::new(contents)),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{path} is not embedded", path = path.display()),
+        )),
+    }
+}
+
+/// Read the whole file into a string, from the embedded files or disk
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    let mut contents = String::new();
+    open(path)?.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::*;
+    use crate::schema::{NewDB, Schema};
+
+    #[test]
+    fn test_embedded_schema() {
+        let schemas = Schema::embedded().expect("embedded toml is bad");
+        assert!(is_embedded(&schemas.lookup_schema));
+
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+        connection
+            .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)
+            .expect("failed to init lookup tables");
+        let count: usize = connection
+            .query_row("SELECT count(*) FROM day_of_week", [], |row| row.get(0))
+            .expect("failed to count");
+        assert_eq!(7, count);
+
+        assert_eq!(
+            fs::read_to_string("schema/crashes.sql").expect("no crashes schema"),
+            read_to_string(&Path::new(EMBEDDED_ROOT).join("schema/crashes.sql"))
+                .expect("crashes schema not embedded")
+        );
+        assert_eq!(
+            io::ErrorKind::NotFound,
+            read_to_string(&Path::new(EMBEDDED_ROOT).join("old-switrs/CollisionRecords.txt"))
+                .expect_err("old-switrs is embedded")
+                .kind()
+        );
+    }
+}
@@ -0,0 +1,507 @@
+//! Interactive review of the roads that couldn't be corrected, the decisions are written back to the typos and
+//!   corrected roads lookup tables
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use log::info;
+use rusqlite::Connection;
+
+use crate::{
+    resources,
+    road::RoadMatcher,
+    schema::{self, FileContext, RoadTables, Schema, SchemaError, SqlContext},
+};
+
+/// Errors from reviewing the roads
+#[derive(Debug, thiserror::Error)]
+pub enum ReviewError {
+    /// The DB or the lookup tables could not be read or written
+    #[error(transparent)]
+    Schema(#[from] SchemaError),
+    /// The answers could not be read, or the questions written
+    #[error("failed to ask the reviewer: {0}")]
+    Prompt(#[from] io::Error),
+    /// The lookup table data is embedded in the binary, so the decisions can't be written to it
+    #[error("can't write to the embedded {path}, review with a Schemas TOML on disk")]
+    Embedded {
+        /// The embedded lookup table data
+        path: PathBuf,
+    },
+}
+
+/// Suggestions shown for each road
+const SUGGESTIONS_PER_ROAD: usize = 3;
+
+/// Lookup table with the typos of the roads, and the known roads
+const TYPOS_TABLE: &str = "berkeley_road_typos";
+
+/// What was written back to the lookup tables by the review
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct ReviewSummary {
+    /// Rows added to the typos
+    pub typos: usize,
+    /// Crashes with corrected roads added or changed
+    pub corrected: usize,
+}
+
+/// A crash with a road that couldn't be corrected
+struct UnresolvedCase {
+    case_id: String,
+    /// The roads as they are in the crash data
+    original: [Option<String>; 2],
+    /// The corrected roads, empty if they couldn't be corrected
+    corrected: [String; 2],
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+}
+
+/// What the reviewer decided for a road
+enum Decision {
+    /// The correct road
+    Correct(String),
+    Skip,
+    Quit,
+}
+
+/// Walk through the crashes with roads that couldn't be corrected by the last build, show each road with its
+///   normalized parts and the closest known roads, and ask the reviewer to accept a suggestion, type the correct road
+///   or skip it
+///
+/// A decision is reused for the same normalized road in the later crashes. The decisions are added to the typos and
+///   the corrected roads lookup tables, in the files of the Schemas TOML, which are kept sorted, as soon as each crash
+///   is finished, so quitting or interrupting the review only loses the crash being reviewed.
+pub fn review_roads(
+    connection: &Connection,
+    schemas: &Schema,
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+) -> Result<ReviewSummary, ReviewError> {
+    let abbreviations = schemas.road_abbreviations()?;
+    let matcher = RoadMatcher::new(schema::known_roads(connection)?);
+
+    // fail before any reviewing if the decisions can't be written
+    let typos_csv = schemas.lookup_table_data(TYPOS_TABLE)?;
+    for path in [
+        &typos_csv,
+        &schemas.lookup_table_data(RoadTables::SWITRS.corrected)?,
+        &schemas.lookup_table_data(RoadTables::CCRS.corrected)?,
+    ] {
+        if resources::is_embedded(path) {
+            return Err(ReviewError::Embedded { path: path.clone() });
+        }
+    }
+
+    // by normalized road, None if it was skipped
+    let mut decisions: HashMap<String, Option<String>> = HashMap::new();
+    let mut summary = ReviewSummary::default();
+
+    for roads in [RoadTables::SWITRS, RoadTables::CCRS] {
+        let corrected_csv = schemas.lookup_table_data(roads.corrected)?;
+        for mut case in unresolved_cases(connection, &roads)? {
+            writeln!(
+                out,
+                "{source} {source_id} {case_id} at {latitude}, {longitude}",
+                source = roads.source,
+                source_id = roads.source_id,
+                case_id = case.case_id,
+                latitude = display_coordinate(case.latitude),
+                longitude = display_coordinate(case.longitude),
+            )?;
+
+            let mut typos = Vec::new();
+            let mut changed = false;
+            let mut quit = false;
+            for ((column, original), corrected) in ["primary_rd", "secondary_rd"]
+                .into_iter()
+                .zip(&case.original)
+                .zip(&mut case.corrected)
+            {
+                let Some(original) = original else {
+                    continue;
+                };
+                writeln!(out, "  {column}: {original}")?;
+                if !corrected.is_empty() {
+                    writeln!(out, "    corrected: {corrected}")?;
+                    continue;
+                }
+
+                let road = abbreviations.normalize_road(original);
+                let normalized = road.road();
+                writeln!(out, "    normalized: {road}")?;
+                if normalized.is_empty() {
+                    continue;
+                }
+
+                if let Some(decision) = decisions.get(normalized.as_ref()) {
+                    match decision {
+                        Some(correct) => {
+                            writeln!(out, "    correct: {correct}, as before")?;
+                            *corrected = correct.clone();
+                            changed = true;
+                        }
+                        None => writeln!(out, "    skipped, as before")?,
+                    }
+                    continue;
+                }
+
+                let suggestions = matcher.suggest(&normalized, SUGGESTIONS_PER_ROAD);
+                for (rank, suggestion) in suggestions.iter().enumerate() {
+                    writeln!(
+                        out,
+                        "    {rank}) {road} ({confidence:.2})",
+                        rank = rank + 1,
+                        road = suggestion.road,
+                        confidence = suggestion.confidence
+                    )?;
+                }
+                let suggestions = suggestions
+                    .iter()
+                    .map(|suggestion| suggestion.road)
+                    .collect::<Vec<_>>();
+
+                match ask(input, out, &suggestions)? {
+                    Decision::Correct(correct) => {
+                        let typo = schema::quoted_csv(&[&normalized, &correct]);
+                        typos.push((vec![normalized.to_string(), correct.clone()], typo));
+                        decisions.insert(normalized.to_string(), Some(correct.clone()));
+                        *corrected = correct;
+                        changed = true;
+                    }
+                    Decision::Skip => {
+                        decisions.insert(normalized.to_string(), None);
+                    }
+                    Decision::Quit => {
+                        quit = true;
+                        break;
+                    }
+                }
+            }
+
+            // the decisions made before quitting are kept
+            summary.typos += merge_csv(&typos_csv, 2, typos)?;
+            if changed {
+                let [primary_rd, secondary_rd] = &case.corrected;
+                let line = format!(
+                    "{},{roads}",
+                    case.case_id,
+                    roads = schema::quoted_csv(&[primary_rd, secondary_rd])
+                );
+                summary.corrected +=
+                    merge_csv(&corrected_csv, 1, vec![(vec![case.case_id], line)])?;
+            }
+            if quit {
+                return Ok(summary);
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// The crashes with a road that isn't empty, but has no corrected road, in the order of their ids
+fn unresolved_cases(
+    connection: &Connection,
+    roads: &RoadTables,
+) -> Result<Vec<UnresolvedCase>, SchemaError> {
+    let RoadTables {
+        source,
+        source_id,
+        source_primary_rd,
+        source_secondary_rd,
+        id,
+        corrected,
+        ..
+    } = roads;
+
+    let mut select_cases = connection
+        .prepare(&format!(
+            "SELECT
+        CAST(s.{source_id} AS TEXT) as case_id,
+        s.{source_primary_rd},
+        s.{source_secondary_rd},
+        c.primary_rd,
+        c.secondary_rd,
+        s.latitude,
+        s.longitude
+        FROM {source} as s
+        LEFT JOIN {corrected} as c ON c.{id} = s.{source_id}
+        WHERE (coalesce(c.primary_rd, '') = '' AND coalesce(s.{source_primary_rd}, '') != '')
+        OR (coalesce(c.secondary_rd, '') = '' AND coalesce(s.{source_secondary_rd}, '') != '')
+        ORDER BY case_id"
+        ))
+        .for_table(source)?;
+
+    let non_empty = |road: Option<String>| road.filter(|road| !road.trim().is_empty());
+    let cases = select_cases
+        .query_map([], |row| {
+            Ok(UnresolvedCase {
+                case_id: row.get(0)?,
+                original: [non_empty(row.get(1)?), non_empty(row.get(2)?)],
+                corrected: [
+                    row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                ],
+                latitude: row.get(5)?,
+                longitude: row.get(6)?,
+            })
+        })
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .for_table(source)?;
+
+    info!(
+        "REVIEWING {count} crashes from {source}",
+        count = cases.len()
+    );
+    Ok(cases)
+}
+
+/// Ask until the reviewer picks a suggestion, types the correct road, skips the road with an empty line or quits
+///   with q, the end of the input also quits
+fn ask(input: &mut dyn BufRead, out: &mut dyn Write, suggestions: &[&str]) -> io::Result<Decision> {
+    loop {
+        if suggestions.is_empty() {
+            write!(out, "  type the correct road, empty to skip, q to quit: ")?;
+        } else {
+            write!(
+                out,
+                "  1-{count} to accept, or type the correct road, empty to skip, q to quit: ",
+                count = suggestions.len()
+            )?;
+        }
+        out.flush()?;
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            writeln!(out)?;
+            return Ok(Decision::Quit);
+        }
+
+        let answer = line.trim();
+        if answer.is_empty() {
+            return Ok(Decision::Skip);
+        }
+        if answer.eq_ignore_ascii_case("q") {
+            return Ok(Decision::Quit);
+        }
+        match answer.parse::<usize>() {
+            Ok(rank) if (1..=suggestions.len()).contains(&rank) => {
+                return Ok(Decision::Correct(suggestions[rank - 1].to_string()))
+            }
+            Ok(rank) => writeln!(out, "  no suggestion {rank}")?,
+            Err(_) => {
+                return Ok(Decision::Correct(
+                    answer
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                        .to_uppercase(),
+                ))
+            }
+        }
+    }
+}
+
+/// The coordinate of the crash, empty if it isn't known
+fn display_coordinate(coordinate: Option<f64>) -> String {
+    coordinate.map(|c| c.to_string()).unwrap_or_default()
+}
+
+/// Add the rows to the CSV, replacing the rows with the same key, the first key_len fields, and write it sorted by
+///   the key, returns the number of rows added or changed
+///
+/// The header stays first, and the rows that weren't replaced are written as they were. The CSV is written to a
+///   temporary file that replaces it, so an interrupted review never leaves it half written.
+fn merge_csv(
+    path: &Path,
+    key_len: usize,
+    rows: Vec<(Vec<String>, String)>,
+) -> Result<usize, SchemaError> {
+    if rows.is_empty() {
+        return Ok(0);
+    }
+
+    let text = resources::read_to_string(path).for_path(path)?;
+    let mut lines = text.lines();
+    let header = lines.next().unwrap_or_default();
+    let mut merged = lines
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut key = fields(line);
+            key.truncate(key_len);
+            (key, line.to_string())
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    let mut count = 0;
+    for (key, line) in rows {
+        if merged.get(&key) != Some(&line) {
+            merged.insert(key, line);
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return Ok(0);
+    }
+
+    info!(
+        "WRITING {count} reviewed rows to {path}",
+        path = path.display()
+    );
+    let mut temp_file = path.as_os_str().to_owned();
+    temp_file.push(".tmp");
+    let temp_file = PathBuf::from(temp_file);
+    let mut file = io::BufWriter::new(fs::File::create(&temp_file).for_path(&temp_file)?);
+    writeln!(file, "{header}").for_path(&temp_file)?;
+    for line in merged.values() {
+        writeln!(file, "{line}").for_path(&temp_file)?;
+    }
+    file.flush().for_path(&temp_file)?;
+    drop(file);
+    fs::rename(&temp_file, path).for_path(path)?;
+
+    Ok(count)
+}
+
+/// The trimmed fields of a CSV line
+fn fields(line: &str) -> Vec<String> {
+    csv::ReaderBuilder::new()
+        .has_headers(false)
+        .trim(csv::Trim::All)
+        .from_reader(line.as_bytes())
+        .into_records()
+        .next()
+        .and_then(Result::ok)
+        .map(|record| record.iter().map(String::from).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, path::PathBuf};
+
+    use super::*;
+    use crate::{
+        schema::{LookupTable, NewDB},
+        test_support::{create_tables, TempDir},
+    };
+
+    #[test]
+    fn test_review_roads() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+        let mut schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        connection
+            .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)
+            .expect("failed to init lookup tables");
+        create_tables(
+            &connection,
+            &[
+                ("switrs_collisions", Some("tests/data/collisions.csv")),
+                ("ccrs_crashes", None),
+            ],
+        );
+
+        // the same typo in two crashes the corrected roads don't have, and a road with quotes in another
+        connection
+            .execute_batch(
+                "UPDATE switrs_collisions SET primary_rd = 'SACRAMNTO ST' WHERE case_id IN ('9629920', '9631228');
+                UPDATE switrs_collisions SET primary_rd = 'THE \"Y\" RD' WHERE case_id = '9631232';
+                UPDATE switrs_corrected_roads SET primary_rd = NULL WHERE case_id IN ('9629920', '9631228', '9631232');",
+            )
+            .expect("failed to update roads");
+
+        // the decisions are written outside of the source tree
+        let review_dir = TempDir::new("review");
+        for (table, file) in [
+            (TYPOS_TABLE, "BERKELEY_ROAD_TYPOS.csv"),
+            ("switrs_corrected_roads", "CORRECTED_ROADS.csv"),
+        ] {
+            let path = review_dir.join(file);
+            fs::copy(Path::new("berkeley-tables").join(file), &path).expect("failed to copy");
+            let table_toml = format!("pk_type = \"NULL\"\ndata = \"{}\"", path.display());
+            let lookup_table: LookupTable = basic_toml::from_str(&table_toml).expect("toml is bad");
+            schemas
+                .lookup_tables
+                .insert(table.to_string(), lookup_table);
+        }
+
+        let mut out = Vec::new();
+        let summary = review_roads(
+            &connection,
+            &schemas,
+            &mut Cursor::new("1\nTHE \"Y\" RD\n"),
+            &mut out,
+        )
+        .expect("review failed");
+        let out = String::from_utf8(out).expect("not utf8");
+        let read = |file: &str| fs::read_to_string(review_dir.join(file)).expect("not written");
+        let typos = read("BERKELEY_ROAD_TYPOS.csv");
+        let corrected = read("CORRECTED_ROADS.csv");
+        let temp_files = fs::read_dir(review_dir.path())
+            .expect("failed to list review dir")
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .is_ok_and(|entry| entry.path().extension().is_some_and(|ext| ext == "tmp"))
+            })
+            .count();
+
+        assert_eq!(
+            ReviewSummary {
+                typos: 2,
+                corrected: 3
+            },
+            summary
+        );
+        assert_eq!(0, temp_files);
+        assert!(out.contains("    1) SACRAMENTO ST ("));
+        assert!(out.contains("    correct: SACRAMENTO ST, as before\n"));
+
+        // the new rows are added in sorted order, the rest are unchanged
+        let checked_in = |file: &str| {
+            fs::read_to_string(PathBuf::from("berkeley-tables").join(file)).expect("not found")
+        };
+        let sorted = |csv: &str| {
+            let keys = csv.lines().skip(1).map(fields).collect::<Vec<_>>();
+            keys.windows(2).all(|pair| pair[0] < pair[1])
+        };
+        assert!(sorted(&typos));
+        assert!(typos.contains("\n\"SACRAMNTO ST\",\"SACRAMENTO ST\"\n"));
+        assert!(typos.contains("\n\"THE \"\"Y\"\" RD\",\"THE \"\"Y\"\" RD\"\n"));
+        assert_eq!(
+            checked_in("BERKELEY_ROAD_TYPOS.csv").lines().count() + 2,
+            typos.lines().count()
+        );
+        assert!(sorted(&corrected));
+        assert!(corrected.contains("\n9629920,\"SACRAMENTO ST\",\"SACRAMENTO ST\"\n"));
+        assert!(corrected.contains("\n9631232,\"THE \"\"Y\"\" RD\",\"SAN PABLO AVE\"\n"));
+        assert_eq!(
+            checked_in("CORRECTED_ROADS.csv").lines().count(),
+            corrected.lines().count()
+        );
+
+        // the quotes are escaped, so the decisions load again
+        let reloaded = Connection::open_in_memory().expect("failed to open in memory DB");
+        let typos_csv = review_dir.join("BERKELEY_ROAD_TYPOS.csv");
+        let corrected_csv = review_dir.join("CORRECTED_ROADS.csv");
+        create_tables(
+            &reloaded,
+            &[
+                (TYPOS_TABLE, typos_csv.to_str()),
+                ("switrs_corrected_roads", corrected_csv.to_str()),
+            ],
+        );
+        let road: String = reloaded
+            .query_row(
+                "SELECT primary_rd FROM switrs_corrected_roads WHERE case_id = '9631232'",
+                [],
+                |row| row.get(0),
+            )
+            .expect("no decision");
+        assert_eq!("THE \"Y\" RD", road);
+    }
+}
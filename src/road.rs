@@ -0,0 +1,804 @@
+//! Parser for the free text road names in the collision reports
+//!
+//! Officers type the primary and secondary roads by hand, so along with the road name they often contain a house
+//!   number, a block, the direction of travel, a second road the party was heading to and notes in parentheses,
+//!   e.g. `CHANNING WAY E/B  800 BLOCK` or `I-80 WB TO UNIVERSITY AVE`. The road is split into words, and the parts
+//!   are taken off the front and back of the words, what's left is the road name.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+/// Directions of travel, or the side of the road, all spellings are kept as is in the NormalizedRoad
+const DIRECTIONS: &[&str] = &[
+    "N",
+    "S",
+    "E",
+    "W",
+    "NB",
+    "SB",
+    "EB",
+    "WB",
+    "N/B",
+    "S/B",
+    "E/B",
+    "W/B",
+    "NORTH",
+    "SOUTH",
+    "EAST",
+    "WEST",
+    "NORTHBOUND",
+    "SOUTHBOUND",
+    "EASTBOUND",
+    "WESTBOUND",
+];
+
+/// Street type suffixes, e.g. `ST` in `CEDAR ST`
+const STREET_TYPES: &[&str] = &[
+    "ALY",
+    "ALLEY",
+    "AV",
+    "AVE",
+    "AVENUE",
+    "BL",
+    "BLVD",
+    "BOULEVARD",
+    "CIR",
+    "CIRCLE",
+    "CT",
+    "COURT",
+    "CRES",
+    "CRESCENT",
+    "DR",
+    "DRIVE",
+    "HWY",
+    "HIGHWAY",
+    "LN",
+    "LANE",
+    "PATH",
+    "PL",
+    "PLACE",
+    "PKWY",
+    "PARKWAY",
+    "RD",
+    "ROAD",
+    "SQ",
+    "SQUARE",
+    "ST",
+    "STREET",
+    "TER",
+    "TERRACE",
+    "TRL",
+    "TRAIL",
+    "WY",
+    "WAY",
+];
+
+/// Words that are followed by a route number, e.g. `RT` in `RT 13`, the number is part of the road name
+const ROUTE_PREFIXES: &[&str] = &[
+    "I",
+    "INTERSTATE",
+    "RT",
+    "ROUTE",
+    "SR",
+    "HWY",
+    "HIGHWAY",
+    "US",
+    "CA",
+];
+
+/// Words for a block, e.g. `BLOCK` in `CEDAR ST, 1800 BLOCK`
+const BLOCKS: &[&str] = &["BLOCK", "BLK"];
+
+fn is_one_of(word: &str, words: &[&str]) -> bool {
+    words.iter().any(|w| w.eq_ignore_ascii_case(word))
+}
+
+fn is_number(word: &str) -> bool {
+    !word.is_empty() && word.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_direction(word: &str) -> bool {
+    is_one_of(word, DIRECTIONS)
+}
+
+fn is_street_type(word: &str) -> bool {
+    is_one_of(word, STREET_TYPES)
+}
+
+/// Joins the words with single spaces, borrowing when there is only one
+fn join<'a>(words: &[&'a str]) -> Cow<'a, str> {
+    match words {
+        [] => Cow::Borrowed(""),
+        [word] => Cow::Borrowed(word),
+        words => Cow::Owned(words.join(" ")),
+    }
+}
+
+/// Joins a second road to the road, e.g. `TO` in `I-80 WB TO UNIVERSITY AVE`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Connector {
+    /// Heading to the road
+    To,
+    /// At the road
+    At,
+    /// Coming from the road
+    From,
+}
+
+impl Connector {
+    /// The word for the connector, e.g. `TO`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::To => "TO",
+            Self::At => "AT",
+            Self::From => "FROM",
+        }
+    }
+
+    fn parse(word: &str) -> Option<Self> {
+        match word.to_ascii_uppercase().as_str() {
+            "TO" => Some(Self::To),
+            "AT" => Some(Self::At),
+            "FROM" => Some(Self::From),
+            _ => None,
+        }
+    }
+}
+
+/// A second road joined to the road, e.g. `TO UNIVERSITY AVE` in `I-80 WB TO UNIVERSITY AVE`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConnectorClause<'a> {
+    /// How the road is joined
+    pub connector: Connector,
+    /// The second road, it has no connectors of its own
+    pub road: NormalizedRoad<'a>,
+}
+
+/// A road name split into its parts, e.g. `1201 2ND ST` has the number `1201`, the name `2ND` and the suffix `ST`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NormalizedRoad<'a> {
+    /// House number, in front of or after the road, e.g. `1201` in `1201 2ND ST` or `1403` in `WARD 1403`
+    pub number: Option<&'a str>,
+    /// Block, i.e. the address at the corner, e.g. `600` in `EUCLID AVE (600 BLOCK)`
+    pub block: Option<&'a str>,
+    /// Direction in front of the road, e.g. `W` in `W COLUSA AV`
+    pub pre_direction: Option<&'a str>,
+    /// The road name without any of the other parts, with single spaces, e.g. `CHANNING` in `CHANNING WAY E/B`
+    pub name: Cow<'a, str>,
+    /// Street type, e.g. `WAY` in `CHANNING WAY E/B`
+    pub suffix: Option<Cow<'a, str>>,
+    /// Direction after the road, e.g. `E/B` in `CHANNING WAY E/B`
+    pub post_direction: Option<&'a str>,
+    /// Second roads joined with TO, AT or FROM
+    pub connectors: Vec<ConnectorClause<'a>>,
+    /// Text in parentheses that isn't a block, e.g. `PARKING LOT` in `1313 NINTH STREET (PARKING LOT)`
+    pub notes: Vec<&'a str>,
+}
+
+/// The road followed by its other parts, e.g. `CHANNING WAY [direction E/B, block 800]` or
+///   `I-80 [direction WB] TO UNIVERSITY AVE`
+impl fmt::Display for NormalizedRoad<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.road())?;
+
+        let parts = [
+            ("number", self.number),
+            ("block", self.block),
+            ("direction", self.direction()),
+        ]
+        .into_iter()
+        .filter_map(|(part, value)| value.map(|value| format!("{part} {value}")))
+        .chain(self.notes.iter().map(|note| format!("({note})")))
+        .collect::<Vec<_>>();
+        if !parts.is_empty() {
+            write!(f, " [{}]", parts.join(", "))?;
+        }
+
+        for ConnectorClause { connector, road } in &self.connectors {
+            write!(f, " {} {road}", connector.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> NormalizedRoad<'a> {
+    /// The road name with the suffix, e.g. `CHANNING WAY` in `CHANNING WAY E/B  800 BLOCK`
+    pub fn road(&self) -> Cow<'a, str> {
+        match &self.suffix {
+            Some(suffix) if self.name.is_empty() => suffix.clone(),
+            Some(suffix) => Cow::Owned(format!("{} {suffix}", self.name)),
+            None => self.name.clone(),
+        }
+    }
+
+    /// The direction of travel, the direction after the road is preferred
+    pub fn direction(&self) -> Option<&'a str> {
+        self.post_direction.or(self.pre_direction)
+    }
+
+    /// Parse the words of a road, without parentheses
+    fn from_words(mut words: &[&'a str]) -> Self {
+        let mut road = Self::default();
+
+        // everything after a connector is a second road, a connector must follow a road,
+        //   the connectors of the second road are added after it
+        if let Some(i) = words
+            .iter()
+            .enumerate()
+            .skip(1)
+            .take(words.len().saturating_sub(2))
+            .find_map(|(i, w)| Connector::parse(w).map(|_| i))
+        {
+            let connector = Connector::parse(words[i]).expect("connector was found");
+            let mut second = Self::from_words(&words[i + 1..]);
+            let mut connectors = std::mem::take(&mut second.connectors);
+            road.connectors.push(ConnectorClause {
+                connector,
+                road: second,
+            });
+            road.connectors.append(&mut connectors);
+            words = &words[..i];
+        }
+
+        // block, e.g. `1400 BLOCK OF CEDAR ST` or `CEDAR ST 1400 BLOCK`
+        match words {
+            [block, b, of, rest @ ..]
+                if is_number(block) && is_one_of(b, BLOCKS) && of.eq_ignore_ascii_case("OF") =>
+            {
+                road.block = Some(block);
+                words = rest;
+            }
+            [rest @ .., block, b]
+                if is_number(block) && is_one_of(b, BLOCKS) && !rest.is_empty() =>
+            {
+                road.block = Some(block);
+                words = rest;
+            }
+            _ => (),
+        }
+
+        // house number, e.g. `1201 2ND ST` or `WARD 1403`, but not the number of a route like `RT 13`
+        match words {
+            [number, rest @ ..] if is_number(number) && rest.iter().any(|w| !is_direction(w)) => {
+                road.number = Some(number);
+                words = rest;
+            }
+            [rest @ .., last, number]
+                if road.block.is_none()
+                    && is_number(number)
+                    && !is_one_of(last, ROUTE_PREFIXES) =>
+            {
+                road.number = Some(number);
+                words = &words[..rest.len() + 1];
+            }
+            _ => (),
+        }
+
+        // direction after the road, e.g. `RT 80 E`
+        if let [rest @ .., direction] = words {
+            if is_direction(direction) && !rest.is_empty() {
+                road.post_direction = Some(direction);
+                words = rest;
+            }
+        }
+
+        // direction in front of the road, e.g. `EAST ASHBY AVE`, but not a street named for one like `WEST ST`
+        if let [direction, rest @ ..] = words {
+            if is_direction(direction) && rest.iter().any(|w| !is_street_type(w)) {
+                road.pre_direction = Some(direction);
+                words = rest;
+            }
+        }
+
+        if let [rest @ .., suffix] = words {
+            if is_street_type(suffix) && !rest.is_empty() {
+                road.suffix = Some(Cow::Borrowed(suffix));
+                words = rest;
+            }
+        }
+
+        road.name = join(words);
+        road
+    }
+}
+
+/// Canonical spellings of the street types and numbered streets, so each road is normalized to one name,
+///   e.g. `ASHBY AVENUE` and `ASHBY AV` are both `ASHBY AVE`, and `6TH` is `SIXTH ST`
+#[derive(Clone, Debug, Default)]
+pub struct RoadAbbreviations {
+    /// The canonical street type for each spelling, e.g. `AVENUE` is `AVE`
+    street_types: HashMap<String, String>,
+    /// The canonical name and the street type of the numbered streets, e.g. `6TH` is `SIXTH` and `ST`
+    numbered_streets: HashMap<String, (String, String)>,
+}
+
+impl RoadAbbreviations {
+    /// Spell the street type as the abbreviation, street types not added are left as they are
+    pub fn add_street_type(&mut self, street_type: &str, abbreviation: &str) {
+        self.street_types.insert(
+            street_type.to_ascii_uppercase(),
+            abbreviation.to_ascii_uppercase(),
+        );
+    }
+
+    /// Spell the numbered street as the name, the street type is added when the road has none,
+    ///   e.g. `6TH` is `SIXTH ST` and `6TH AVENUE` is `SIXTH AVE`
+    pub fn add_numbered_street(&mut self, number: &str, name: &str, street_type: &str) {
+        self.numbered_streets.insert(
+            number.to_ascii_uppercase(),
+            (name.to_ascii_uppercase(), street_type.to_ascii_uppercase()),
+        );
+    }
+
+    /// Parse the road like normalize_road, with the street types and numbered streets of the road and its
+    ///   connected roads spelled canonically
+    pub fn normalize_road<'a>(&self, road: &'a str) -> NormalizedRoad<'a> {
+        let mut normalized = normalize_road(road);
+        self.canonicalize(&mut normalized);
+        for connected in &mut normalized.connectors {
+            self.canonicalize(&mut connected.road);
+        }
+
+        normalized
+    }
+
+    fn canonicalize(&self, road: &mut NormalizedRoad<'_>) {
+        if let Some((name, street_type)) =
+            self.numbered_streets.get(&road.name.to_ascii_uppercase())
+        {
+            road.name = Cow::Owned(name.clone());
+            if road.suffix.is_none() {
+                road.suffix = Some(Cow::Owned(street_type.clone()));
+            }
+        }
+
+        let abbreviation = road
+            .suffix
+            .as_ref()
+            .and_then(|suffix| self.street_types.get(&suffix.to_ascii_uppercase()));
+        if let Some(abbreviation) = abbreviation {
+            road.suffix = Some(Cow::Owned(abbreviation.clone()));
+        }
+    }
+}
+
+/// A known road that may be the road that couldn't be corrected, see RoadMatcher
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoadSuggestion<'k> {
+    /// The known road, e.g. a `correct_rd` in the berkeley_road_typos
+    pub road: &'k str,
+    /// How alike the roads are, from 0 for nothing in common to 1 for the same road
+    pub confidence: f64,
+}
+
+/// Ranks the known roads by how alike they are to a road, to suggest corrections for roads that aren't known
+///
+/// The confidence is a weighted mix of the edit distance of the roads, the edit distance of their words, and the words
+///   that sound alike (by their Soundex codes), so `SHATUCK AVE` and `SHATTUCK AVE` are close, and so are
+///   `ALCATRAZ` and `ALCATRAZ AVE`. The street types aren't compared as words, `SHATUCK AVE` is more like
+///   `SHATTUCK PL` than `ALCATRAZ AVE`.
+#[derive(Clone, Debug, Default)]
+pub struct RoadMatcher {
+    /// Each known road, with its words and the Soundex codes of its words
+    known: Vec<(String, BTreeSet<String>, BTreeSet<String>)>,
+}
+
+impl RoadMatcher {
+    const EDIT_WEIGHT: f64 = 0.5;
+    const TOKEN_WEIGHT: f64 = 0.3;
+    const PHONETIC_WEIGHT: f64 = 0.2;
+
+    /// Match against the known roads, duplicates are ignored
+    pub fn new(known: impl IntoIterator<Item = String>) -> Self {
+        let known = known.into_iter().collect::<BTreeSet<_>>();
+        Self {
+            known: known
+                .into_iter()
+                .map(|road| {
+                    let (words, sounds) = words_and_sounds(&road);
+                    (road, words, sounds)
+                })
+                .collect(),
+        }
+    }
+
+    /// The known roads most like the road, at most limit of them, with the most confident first
+    pub fn suggest(&self, road: &str, limit: usize) -> Vec<RoadSuggestion<'_>> {
+        let (words, sounds) = words_and_sounds(road);
+        let mut suggestions = self
+            .known
+            .iter()
+            .map(|(known, known_words, known_sounds)| {
+                let confidence = Self::EDIT_WEIGHT * strsim::normalized_levenshtein(road, known)
+                    + Self::TOKEN_WEIGHT * word_similarity(&words, known_words)
+                    + Self::PHONETIC_WEIGHT * jaccard(&sounds, known_sounds);
+                RoadSuggestion {
+                    road: known,
+                    confidence,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // ties are kept in the order of the names, so the suggestions are stable
+        suggestions.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+/// The words of the road and their Soundex codes, without the street type, which so many roads share
+fn words_and_sounds(road: &str) -> (BTreeSet<String>, BTreeSet<String>) {
+    let words = road
+        .split_whitespace()
+        .filter(|w| !is_street_type(w))
+        .map(str::to_ascii_uppercase)
+        .collect::<BTreeSet<_>>();
+    let sounds = words.iter().filter_map(|w| soundex(w)).collect();
+    (words, sounds)
+}
+
+/// How alike the words are, each word is matched to the most alike word of the other road by edit distance,
+///   and the matches of both roads are averaged, 1 for two empty sets
+fn word_similarity(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    let matched = |a: &BTreeSet<String>, b: &BTreeSet<String>| {
+        a.iter()
+            .map(|a| {
+                b.iter()
+                    .map(|b| strsim::normalized_levenshtein(a, b))
+                    .fold(0.0, f64::max)
+            })
+            .sum::<f64>()
+    };
+
+    let count = a.len() + b.len();
+    if count == 0 {
+        return 1.0;
+    }
+    (matched(a, b) + matched(b, a)) / count as f64
+}
+
+/// Size of the intersection over the size of the union, 1 for two empty sets
+fn jaccard(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// American Soundex code of the word, e.g. `R163` for `ROBERT` and `RUPERT`, words starting with a number are
+///   kept as they are, None for words without letters or numbers
+fn soundex(word: &str) -> Option<String> {
+    let first = word.chars().find(char::is_ascii_alphanumeric)?;
+    if first.is_ascii_digit() {
+        return Some(word.to_string());
+    }
+
+    let digit = |c: char| match c.to_ascii_uppercase() {
+        'B' | 'F' | 'P' | 'V' => Some('1'),
+        'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+        'D' | 'T' => Some('3'),
+        'L' => Some('4'),
+        'M' | 'N' => Some('5'),
+        'R' => Some('6'),
+        _ => None,
+    };
+
+    let mut code = String::from(first.to_ascii_uppercase());
+    let mut last = digit(first);
+    for c in word.chars().filter(char::is_ascii_alphabetic).skip(1) {
+        let d = digit(c);
+        if d.is_some() && d != last {
+            code.extend(d);
+        }
+        // H and W don't separate letters with the same code, vowels do
+        if !matches!(c.to_ascii_uppercase(), 'H' | 'W') {
+            last = d;
+        }
+        if code.len() == 4 {
+            break;
+        }
+    }
+
+    Some(format!("{code:0<4}"))
+}
+
+/// A word of the road, or a parenthesized note
+enum Token<'a> {
+    Word(&'a str),
+    Parenthesized(&'a str),
+}
+
+/// Splits the road into words on spaces, commas and periods, text in parentheses is kept together
+fn tokenize(road: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = road;
+    while let Some(start) = rest.find(|c: char| !is_separator(c)) {
+        rest = &rest[start..];
+        if let Some(inner) = rest.strip_prefix('(') {
+            let end = inner.find(')').unwrap_or(inner.len());
+            tokens.push(Token::Parenthesized(inner[..end].trim()));
+            rest = inner.get(end + 1..).unwrap_or("");
+        } else {
+            let end = rest
+                .find(|c: char| is_separator(c) || c == '(')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Word(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+
+    tokens
+}
+
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || matches!(c, ',' | '.' | ';' | ')')
+}
+
+/// Takes road names and splits off the address, block, direction, connected roads and notes
+pub fn normalize_road(road: &str) -> NormalizedRoad<'_> {
+    let mut words = Vec::new();
+    let mut parenthesized = Vec::new();
+    for token in tokenize(road) {
+        match token {
+            Token::Word(word) => words.push(word),
+            Token::Parenthesized(note) => parenthesized.push(note),
+        }
+    }
+
+    let mut normalized = NormalizedRoad::from_words(&words);
+    for note in parenthesized {
+        // e.g. `EUCLID AVE (600 BLOCK)`
+        match note.split_whitespace().collect::<Vec<_>>()[..] {
+            [block, b]
+                if normalized.block.is_none() && is_number(block) && is_one_of(b, BLOCKS) =>
+            {
+                normalized.block = Some(block)
+            }
+            _ if note.is_empty() => (),
+            _ => normalized.notes.push(note),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_road() {
+        let test = |raw, road: &str, address, block, direction| {
+            let normalized = normalize_road(raw);
+            assert_eq!(
+                (road, address, block, direction),
+                (
+                    normalized.road().as_ref(),
+                    normalized.number,
+                    normalized.block,
+                    normalized.direction()
+                ),
+                "{raw}"
+            )
+        };
+
+        test("GRANT", "GRANT", None, None, None);
+        test("1201 2ND ST", "2ND ST", Some("1201"), None, None);
+        test("WARD 1403", "WARD", Some("1403"), None, None);
+        test("RT 123", "RT 123", None, None, None);
+        test("RT 13", "RT 13", None, None, None);
+        test("RT 80 E", "RT 80", None, None, Some("E"));
+        test("RT1805", "RT1805", None, None, None);
+        test("SAN PABLO 1229", "SAN PABLO", Some("1229"), None, None);
+        test("6TH  ST", "6TH ST", None, None, None);
+        test("7 GAUSS WAY", "GAUSS WAY", Some("7"), None, None);
+        test("ASHBY AVE.", "ASHBY AVE", None, None, None);
+        test(
+            "EUCLID AVE (600 BLOCK)",
+            "EUCLID AVE",
+            None,
+            Some("600"),
+            None,
+        );
+        test("RT 80 E/B", "RT 80", None, None, Some("E/B"));
+        test("I-80 WB TO UNIVERSITY AVE", "I-80", None, None, Some("WB"));
+        test("I-80 E/B TO I-580 W/B", "I-80", None, None, Some("E/B"));
+        test(
+            "1313 NINTH STREET (PARKING LOT)",
+            "NINTH STREET",
+            Some("1313"),
+            None,
+            None,
+        );
+        test(
+            "85 EL CAMINO REAL RD",
+            "EL CAMINO REAL RD",
+            Some("85"),
+            None,
+            None,
+        );
+        test(
+            "8TH STREET, 1400 BLOCK",
+            "8TH STREET",
+            None,
+            Some("1400"),
+            None,
+        );
+        test(
+            "ADDISON ST. WESTBOUND, 1500 BLOCK",
+            "ADDISON ST",
+            None,
+            Some("1500"),
+            Some("WESTBOUND"),
+        );
+        test(
+            "CEDAR ST. (2200 BLOCK)",
+            "CEDAR ST",
+            None,
+            Some("2200"),
+            None,
+        );
+        test(
+            "CEDAR STREET, 1800 BLOCK",
+            "CEDAR STREET",
+            None,
+            Some("1800"),
+            None,
+        );
+        test(
+            "CHANNING WAY E/B  800 BLOCK",
+            "CHANNING WAY",
+            None,
+            Some("800"),
+            Some("E/B"),
+        );
+        test(
+            "CAMPUS DR, 1400 BLOCK",
+            "CAMPUS DR",
+            None,
+            Some("1400"),
+            None,
+        );
+        test(
+            "CAMPUS DR., 1400 BLOCK",
+            "CAMPUS DR",
+            None,
+            Some("1400"),
+            None,
+        );
+        test("W COLUSA AV", "COLUSA AV", None, None, Some("W"));
+        test("EAST ASHBY AVE", "ASHBY AVE", None, None, Some("EAST"));
+        test("WEST ST", "WEST ST", None, None, None);
+        test("ASHBY AV 1200 BLK", "ASHBY AV", None, Some("1200"), None);
+        test(
+            "1400 BLOCK OF CEDAR ST",
+            "CEDAR ST",
+            None,
+            Some("1400"),
+            None,
+        );
+        test(
+            "MARTIN LUTHER KING JR WY S",
+            "MARTIN LUTHER KING JR WY",
+            None,
+            None,
+            Some("S"),
+        );
+    }
+
+    #[test]
+    fn test_road_abbreviations() {
+        let mut abbreviations = RoadAbbreviations::default();
+        abbreviations.add_street_type("AVENUE", "AVE");
+        abbreviations.add_street_type("AV", "AVE");
+        abbreviations.add_street_type("STREET", "ST");
+        abbreviations.add_numbered_street("6TH", "SIXTH", "ST");
+        abbreviations.add_numbered_street("10TH", "TENTH", "ST");
+
+        let test =
+            |raw, road: &str| assert_eq!(road, abbreviations.normalize_road(raw).road(), "{raw}");
+        test("ASHBY AVENUE", "ASHBY AVE");
+        test("W COLUSA AV", "COLUSA AVE");
+        test("CEDAR STREET, 1800 BLOCK", "CEDAR ST");
+        test("CEDAR ST", "CEDAR ST");
+        test("CHANNING WAY E/B  800 BLOCK", "CHANNING WAY");
+        test("6TH", "SIXTH ST");
+        test("10TH STREET", "TENTH ST");
+        test("6TH AVENUE", "SIXTH AVE");
+        test("53RD ST", "53RD ST");
+        test("RT 13", "RT 13");
+
+        let road = abbreviations.normalize_road("I-80 WB TO ASHBY AVENUE");
+        assert_eq!("ASHBY AVE", road.connectors[0].road.road());
+    }
+
+    #[test]
+    fn test_road_matcher() {
+        assert_eq!(Some("R163"), soundex("ROBERT").as_deref());
+        assert_eq!(Some("R163"), soundex("RUPERT").as_deref());
+        assert_eq!(Some("A261"), soundex("ASHCRAFT").as_deref());
+        assert_eq!(Some("T522"), soundex("TYMCZAK").as_deref());
+        assert_eq!(Some("P236"), soundex("PFISTER").as_deref());
+        assert_eq!(Some("62ND"), soundex("62ND").as_deref());
+
+        let matcher = RoadMatcher::new(
+            [
+                "SHATTUCK AVE",
+                "SHATTUCK PL",
+                "ALCATRAZ AVE",
+                "ASHBY AVE",
+                "ASHBY AVE",
+            ]
+            .map(String::from),
+        );
+        let suggestions = matcher.suggest("SHATUCK AVE", 2);
+        assert_eq!(
+            vec!["SHATTUCK AVE", "SHATTUCK PL"],
+            suggestions.iter().map(|s| s.road).collect::<Vec<_>>()
+        );
+        assert!(suggestions[0].confidence > 0.8);
+
+        assert_eq!("ALCATRAZ AVE", matcher.suggest("ALCATRAZ", 1)[0].road);
+        assert_eq!(1.0, matcher.suggest("ASHBY AVE", 1)[0].confidence);
+        assert_eq!(4, matcher.suggest("ASHBY AVE", 10).len());
+    }
+
+    #[test]
+    fn test_road_parts() {
+        let road = normalize_road("I-80 WB TO UNIVERSITY AVE");
+        assert_eq!("I-80", road.name);
+        assert_eq!(Some("WB"), road.post_direction);
+        assert_eq!(
+            vec![ConnectorClause {
+                connector: Connector::To,
+                road: NormalizedRoad {
+                    name: Cow::Borrowed("UNIVERSITY"),
+                    suffix: Some(Cow::Borrowed("AVE")),
+                    ..NormalizedRoad::default()
+                }
+            }],
+            road.connectors
+        );
+
+        let road = normalize_road("I-80 E/B TO I-580 W/B FROM ASHBY AVE");
+        let connected = road
+            .connectors
+            .iter()
+            .map(|c| (c.connector, c.road.road(), c.road.direction()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (Connector::To, Cow::Borrowed("I-580"), Some("W/B")),
+                (Connector::From, Cow::Borrowed("ASHBY AVE"), None)
+            ],
+            connected
+        );
+
+        let road = normalize_road("1313 NINTH STREET (PARKING LOT)");
+        assert_eq!(Some("1313"), road.number);
+        assert_eq!("NINTH", road.name);
+        assert_eq!(Some(Cow::Borrowed("STREET")), road.suffix);
+        assert_eq!(vec!["PARKING LOT"], road.notes);
+
+        assert_eq!(
+            "NINTH STREET [number 1313, (PARKING LOT)]",
+            road.to_string()
+        );
+        assert_eq!(
+            "CHANNING WAY [block 800, direction E/B]",
+            normalize_road("CHANNING WAY E/B  800 BLOCK").to_string()
+        );
+        assert_eq!(
+            "I-80 [direction E/B] TO I-580 [direction W/B]",
+            normalize_road("I-80 E/B TO I-580 W/B").to_string()
+        );
+
+        let road = normalize_road("FROM GILMAN STREET");
+        assert_eq!("FROM GILMAN STREET", road.road());
+        assert!(road.connectors.is_empty());
+    }
+}
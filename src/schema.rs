@@ -1,20 +1,238 @@
 //! Schema operations for the SWITRS sqlite DB creation
 
 use std::{
-    borrow::Cow,
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fs,
-    io::Write,
+    io::{self, Read, Write},
+    iter, mem,
     path::{Path, PathBuf},
-    sync::OnceLock,
+    sync::{
+        mpsc::{self, SyncSender},
+        Arc, Condvar, Mutex, OnceLock, PoisonError,
+    },
+    thread,
 };
 
 use heck::ToSnakeCase;
-use log::{debug, error, info, log_enabled, warn, Level};
-use new_string_template::template::Template;
+use log::{debug, error, info, log, log_enabled, warn, Level};
+use new_string_template::{error::TemplateError, template::Template};
 use regex::Regex;
-use rusqlite::{params_from_iter, Connection};
+use rusqlite::{params_from_iter, Connection, Row};
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    resources,
+    road::{NormalizedRoad, RoadAbbreviations, RoadMatcher},
+};
+
+/// Errors from loading the Schemas TOML and building the DB
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    /// A file could not be read or written
+    #[error("failed to access {path}: {source}")]
+    Io {
+        /// The file
+        path: PathBuf,
+        /// The error from reading or writing the file
+        source: io::Error,
+    },
+    /// The Schemas TOML could not be parsed
+    #[error("failed to parse {path}: {source}")]
+    Toml {
+        /// The Schemas TOML
+        path: PathBuf,
+        /// The error from parsing the TOML
+        source: basic_toml::Error,
+    },
+    /// A table is used, but is missing from a section of the Schemas TOML
+    #[error("table missing from [{section}]: {table}")]
+    MissingTable {
+        /// The table
+        table: String,
+        /// The section of the Schemas TOML the table should be in
+        section: &'static str,
+    },
+    /// The schema for a table is not a valid template
+    #[error("bad template in {path} for {table}: {source}")]
+    Template {
+        /// The table
+        table: String,
+        /// The schema file
+        path: PathBuf,
+        /// The error from rendering the template
+        source: TemplateError,
+    },
+    /// The file name pattern for the data of a CCRS table is not a valid regex
+    #[error("bad file name pattern for {table}: {source}")]
+    Regex {
+        /// The table
+        table: String,
+        /// The error from compiling the regex
+        source: regex::Error,
+    },
+    /// The CCRS data directory does not exist
+    #[error("ccrs data directory not found: {path}")]
+    MissingDataDir {
+        /// The CCRS data directory
+        path: PathBuf,
+    },
+    /// A CSV file could not be read
+    #[error("failed to read csv {path}: {source}")]
+    Csv {
+        /// The CSV file
+        path: PathBuf,
+        /// The error from reading the CSV
+        source: csv::Error,
+    },
+    /// The headers of a CSV file don't match the columns of the table
+    #[error("headers in {path} don't match the columns of {table}: {message}")]
+    Headers {
+        /// The table
+        table: String,
+        /// The CSV file
+        path: PathBuf,
+        /// What doesn't match, with hints for fixing it
+        message: String,
+    },
+    /// A record from a CSV file could not be inserted into the table
+    #[error("failed to insert line {line} of {path} into {table}: {source}")]
+    Insert {
+        /// The table
+        table: String,
+        /// The CSV file
+        path: PathBuf,
+        /// Line of the record in the CSV file
+        line: u64,
+        /// The error from the insert
+        source: rusqlite::Error,
+    },
+    /// More records failed to load than the error-budget of the table allows
+    #[error("{errored} records failed to load into {table}, over the error-budget of {error_budget}, see the load_rejections in the log")]
+    ErrorBudget {
+        /// The table
+        table: String,
+        /// Number of records that failed to load
+        errored: usize,
+        /// The error-budget of the table
+        error_budget: usize,
+    },
+    /// The table is not in the DB
+    #[error("no such table: {0}")]
+    NoSuchTable(String),
+    /// A query on the table failed
+    #[error("sql error on {table}: {source}")]
+    Sql {
+        /// The table
+        table: String,
+        /// The error from sqlite
+        source: rusqlite::Error,
+    },
+    /// The corrected roads differ from the checked in ones, see Corrections::Check
+    #[error("{table} in {path} is out of date, {removed} lines removed and {added} added, run with `--corrections write` to update it")]
+    CorrectionsOutOfDate {
+        /// The corrected roads table
+        table: String,
+        /// The checked in corrected roads
+        path: PathBuf,
+        /// Number of lines only in the checked in file
+        removed: usize,
+        /// Number of lines only in the corrected roads
+        added: usize,
+    },
+    /// Rows have codes that aren't in the tables their FOREIGN KEYs reference, and the build is strict
+    #[error("{rows} rows have {codes} unknown codes, see the UNKNOWN warnings in the log")]
+    ForeignKeyViolations {
+        /// Number of distinct unknown codes, by table and column
+        codes: usize,
+        /// Number of rows with an unknown code
+        rows: usize,
+    },
+    /// The tables depend on each other, so there is no order to load them in
+    #[error("dependency cycle between tables: {}", .0.join(" -> "))]
+    DependencyCycle(Vec<String>),
+    /// The built DB failed the checks, see NewDB::check_build
+    #[error("bad build: {0}")]
+    BadBuild(String),
+    /// The Schemas TOML has mistakes that would fail or skip part of the build, see Schema::validate
+    #[error("{count} problems in the Schemas TOML:\n{list}", count = .0.len(), list = problem_list(.0))]
+    Invalid(Vec<SchemaProblem>),
+}
+
+/// A mistake in the Schemas TOML, found before any data is loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaProblem {
+    /// The section of the Schemas TOML, like tables or lookup-tables
+    pub section: &'static str,
+    /// The table with the problem
+    pub table: String,
+    /// What is wrong
+    pub problem: String,
+}
+
+impl SchemaProblem {
+    fn new(section: &'static str, table: &str, problem: impl Into<String>) -> Self {
+        Self {
+            section,
+            table: table.to_string(),
+            problem: problem.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for SchemaProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}: {}", self.section, self.table, self.problem)
+    }
+}
+
+/// The problems, one per line
+fn problem_list(problems: &[SchemaProblem]) -> String {
+    problems
+        .iter()
+        .map(|p| format!("  {p}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Adds the table to sqlite errors
+pub(crate) trait SqlContext<T> {
+    /// The error as a SchemaError::Sql for the table
+    fn for_table(self, table: &str) -> Result<T, SchemaError>;
+}
+
+impl<T> SqlContext<T> for rusqlite::Result<T> {
+    fn for_table(self, table: &str) -> Result<T, SchemaError> {
+        self.map_err(|source| SchemaError::Sql {
+            table: table.to_string(),
+            source,
+        })
+    }
+}
+
+/// Adds the path to file errors
+pub(crate) trait FileContext<T> {
+    /// The error as a SchemaError for the file at the path
+    fn for_path(self, path: &Path) -> Result<T, SchemaError>;
+}
+
+impl<T> FileContext<T> for io::Result<T> {
+    fn for_path(self, path: &Path) -> Result<T, SchemaError> {
+        self.map_err(|source| SchemaError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+impl<T> FileContext<T> for csv::Result<T> {
+    fn for_path(self, path: &Path) -> Result<T, SchemaError> {
+        self.map_err(|source| SchemaError::Csv {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
 
 /// Specifies which schema and data should be used for creating a table
 #[derive(Debug, Deserialize)]
@@ -47,397 +265,2668 @@ pub struct PrimaryTable {
     /// Path to the data to load into the table
     #[serde(flatten)]
     data: DataPath,
+
+    /// Maximum number of records that may fail to parse or insert before the load fails, unlimited if not set
+    #[serde(default, alias = "error-budget")]
+    error_budget: Option<usize>,
+
+    /// CSV headers that have been renamed, mapped to the column in the table they should be loaded into
+    #[serde(default, alias = "header-aliases")]
+    header_aliases: HashMap<String, String>,
+
+    /// Column with the city name, when building for a jurisdiction only rows for its cities are loaded
+    #[serde(default, alias = "jurisdiction-column")]
+    jurisdiction_column: Option<String>,
+
+    /// When building for a jurisdiction only rows with a key in the parent table are loaded
+    #[serde(default)]
+    parent: Option<ParentTable>,
+
+    /// Tables that must be loaded first, like the tables used by views, the parent, the tables referenced
+    ///   by FOREIGN KEYs in the schema, and ccrs_imports for CCRS data are always loaded first
+    #[serde(default, alias = "depends-on")]
+    depends_on: Vec<String>,
+}
+
+/// A table that the rows of another table belong to, like the crash of a party
+#[derive(Debug, Deserialize)]
+pub struct ParentTable {
+    /// Name of the parent table, which must be loaded first
+    table: String,
+    /// Column with the key of the parent, with the same name in both tables
+    key: String,
+}
+
+impl PrimaryTable {
+    /// Options for loading the data files of the table
+    fn load_options<'a>(
+        &'a self,
+        on_conflict: OnConflict,
+        batch_size: usize,
+        filter: Option<&'a RowFilter>,
+    ) -> LoadOptions<'a> {
+        LoadOptions {
+            on_conflict,
+            report_new_entries: false,
+            header_aliases: Some(&self.header_aliases),
+            batch_size,
+            filter,
+            release: None,
+            parent: self.parent.as_ref(),
+        }
+    }
+
+    /// All the tables that must be loaded before this one, see depends_on
+    fn dependencies(&self, table_name: &str) -> Result<BTreeSet<String>, SchemaError> {
+        let mut dependencies = schema_references(&self.schema, table_name)?;
+        dependencies.extend(self.depends_on.iter().cloned());
+        if let Some(ParentTable { table, .. }) = &self.parent {
+            dependencies.insert(table.clone());
+        }
+        // the CCRS files loaded are recorded in the imports table
+        if let DataPath::CcrsData(_) = self.data {
+            dependencies.insert(String::from(CCRS_IMPORTS_TABLE));
+        }
+
+        Ok(dependencies)
+    }
+
+    /// Fail the load if more records were rejected with errors than the error budget allows
+    fn check_error_budget(&self, table_name: &str, errored: usize) -> Result<(), SchemaError> {
+        match self.error_budget {
+            Some(error_budget) if errored > error_budget => Err(SchemaError::ErrorBudget {
+                table: table_name.to_string(),
+                errored,
+                error_budget,
+            }),
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Schema defenition as loaded from the Toml
 #[derive(Debug, Deserialize)]
 pub struct Schema {
-    #[serde(alias = "table-order")]
-    table_order: Vec<String>,
     tables: HashMap<String, PrimaryTable>,
     #[serde(alias = "lookup-schema")]
-    lookup_schema: PathBuf,
+    pub(crate) lookup_schema: PathBuf,
     #[serde(alias = "lookup-tables")]
-    lookup_tables: HashMap<String, LookupTable>,
+    pub(crate) lookup_tables: HashMap<String, LookupTable>,
+    #[serde(default)]
+    jurisdiction: Jurisdiction,
+    /// Number of records loaded in each transaction
+    #[serde(default, alias = "batch-size")]
+    batch_size: Option<usize>,
+    /// Number of threads parsing the CCRS files while they are loaded
+    #[serde(default, alias = "parse-workers")]
+    parse_workers: Option<usize>,
+    /// Files the corrected road names for each crash are written to, by the name of the corrected roads table
+    #[serde(default, alias = "corrected-roads")]
+    corrected_roads: HashMap<String, PathBuf>,
+    /// File the ranked suggestions for the roads that couldn't be corrected are written to, none are written if not set
+    #[serde(default, alias = "road-suggestions")]
+    road_suggestions: Option<PathBuf>,
+    /// Files with the canonical spellings of the street types and numbered streets in the normalized roads
+    #[serde(default, alias = "road-abbreviations")]
+    road_abbreviations: RoadAbbreviationFiles,
+    /// Whether the corrected roads are written, or only checked
+    #[serde(skip)]
+    corrections: Corrections,
+    /// Fail the build if any rows have codes that aren't in the tables their FOREIGN KEYs reference
+    #[serde(skip)]
+    strict_foreign_keys: bool,
+    /// Directory of the Toml file, all the relative paths in the Toml are relative to it
+    #[serde(skip)]
+    base_dir: PathBuf,
+    /// SHA-256 of the Toml file, recorded in the build_manifest
+    #[serde(skip)]
+    sha256: String,
 }
 
 impl Schema {
-    /// Loads the Schema definition from the Toml at the given path
-    pub fn from_toml_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
-        let schema = basic_toml::from_slice(&fs::read(path)?)?;
+    /// Loads the Schema definition from the Toml at the given path, relative paths in the Toml are resolved against
+    ///   the directory of the Toml, not the current directory
+    pub fn from_toml_file(path: &Path) -> Result<Self, SchemaError> {
+        let toml = fs::read(path).for_path(path)?;
+        Self::from_toml(&toml, path, path.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Loads the default Schema definition embedded in the binary, see the resources module
+    ///
+    /// The corrected roads can't be written to the embedded files, so they are written to the current directory.
+    pub fn embedded() -> Result<Self, SchemaError> {
+        let base_dir = Path::new(resources::EMBEDDED_ROOT);
+        let mut schema = Self::from_toml(
+            resources::SCHEMAS_TOML.as_bytes(),
+            &base_dir.join("Schemas.toml"),
+            base_dir,
+        )?;
+        schema.set_corrected_roads_dir(Path::new(""));
 
         Ok(schema)
     }
-}
 
-/// Extensions to the DB Connection to initialize the DB
-pub trait NewDB {
-    /// Get access to the DB connection (generally will be Self)
-    fn connection(&self) -> &Connection;
+    /// Parse the Toml read from the path, with the relative paths resolved against the base directory
+    fn from_toml(toml: &[u8], path: &Path, base_dir: &Path) -> Result<Self, SchemaError> {
+        let mut schema: Self =
+            basic_toml::from_slice(toml).map_err(|source| SchemaError::Toml {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        schema.sha256 = format!("{:x}", Sha256::digest(toml));
+        schema.rebase(base_dir);
 
-    /// Create a table where the name and pk_type are passed into the sql as template parameters
-    fn create_table(
-        &self,
-        name: &str,
-        pk_type: &str,
-        table_schema: &Path,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        // build the DDL expression
-        let ddl = fs::read_to_string(table_schema).map_err(|e| {
-            format!(
-                "failed to read {table_schema}: {e}",
-                table_schema = table_schema.display()
-            )
-        })?;
-        let ddl = Template::new(ddl);
-        let data = {
-            let mut map = HashMap::new();
-            map.insert("table", name);
-            map.insert("pk_type", pk_type);
-            map
-        };
+        Ok(schema)
+    }
 
-        let ddl = ddl.render(&data)?;
-        self.connection().execute_batch(&ddl)?;
-        Ok(())
+    /// Resolve all the paths in the Schema against the base directory, absolute paths are left as is
+    fn rebase(&mut self, base_dir: &Path) {
+        let rebase = |path: &mut PathBuf| *path = base_dir.join(&*path);
+
+        rebase(&mut self.lookup_schema);
+        for table in self.lookup_tables.values_mut() {
+            rebase(&mut table.data);
+            if let Some(schema) = &mut table.schema {
+                rebase(schema);
+            }
+        }
+        for table in self.tables.values_mut() {
+            rebase(&mut table.schema);
+            if let DataPath::Path(path) = &mut table.data {
+                rebase(path);
+            }
+        }
+        for path in self.corrected_roads.values_mut() {
+            rebase(path);
+        }
+        for path in self.road_abbreviations.paths_mut() {
+            rebase(path);
+        }
+        if let Some(path) = &mut self.road_suggestions {
+            rebase(path);
+        }
+
+        self.base_dir = base_dir.to_path_buf();
     }
 
-    /// Load data into the named table from the CSV file at the given table_data path
-    fn load_data(
-        &self,
-        name: &str,
-        table_data: &Path,
-    ) -> Result<usize, Box<dyn std::error::Error>> {
-        self.load_data_with_options(name, table_data, true, false)
+    /// Resolve the path against the directory of the Toml, like the paths in the Toml,
+    ///   or the current directory for the embedded Toml
+    pub fn resolve_path(&self, path: &Path) -> PathBuf {
+        if resources::is_embedded(&self.base_dir) {
+            return path.to_path_buf();
+        }
+        self.base_dir.join(path)
     }
 
-    /// Load data into the named table from the CSV file at the given table_data path
-    fn load_data_with_options(
-        &self,
-        name: &str,
-        table_data: &Path,
-        allow_duplicates: bool,
-        report_new_entries: bool,
-    ) -> Result<usize, Box<dyn std::error::Error>> {
-        // open the csv file
-        let mut csv = csv::ReaderBuilder::new()
-            .quoting(true)
-            .has_headers(true)
-            .trim(csv::Trim::All)
-            .from_path(table_data)
-            .map_err(|e| {
-                format!(
-                    "failed to read csv {table_data}: {e}",
-                    table_data = table_data.display()
-                )
-            })?;
+    /// Replace the jurisdiction from the Toml, e.g. with one from the command line
+    pub fn set_jurisdiction(&mut self, jurisdiction: Jurisdiction) {
+        self.jurisdiction = jurisdiction;
+    }
 
-        // build up the insert statement
-        let mut field_count = 0;
-        let headers_record;
+    /// Replace the batch size from the Toml, e.g. with one from the command line
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = Some(batch_size);
+    }
 
-        let (fields, values) = {
-            // construct "field = "
-            headers_record = csv.headers()?.clone();
-            let mut fields = String::new();
-            let mut values = String::new();
-            let mut first = true;
-            for f in headers_record.into_iter() {
-                if !first {
-                    fields.push_str(", ");
-                    values.push_str(", ");
-                } else {
-                    first = false;
-                }
+    /// Number of records loaded in each transaction, DEFAULT_BATCH_SIZE if not set in the Toml
+    pub fn batch_size(&self) -> usize {
+        self.batch_size.unwrap_or(DEFAULT_BATCH_SIZE)
+    }
 
-                // fixup names with spaces
-                let f = f.to_snake_case();
-                //let f = f.replace(" ", "_");
+    /// Replace the number of parse workers from the Toml, e.g. with one from the command line
+    pub fn set_parse_workers(&mut self, parse_workers: usize) {
+        self.parse_workers = Some(parse_workers);
+    }
 
-                fields.push_str(&f);
-                values.push('?');
-                field_count += 1;
-            }
+    /// Number of threads parsing the CCRS files while they are loaded, one per CPU if not set in the Toml
+    pub fn parse_workers(&self) -> usize {
+        self.parse_workers
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, usize::from))
+    }
 
-            (fields, values)
-        };
+    /// Write the road suggestions to the file, instead of the file in the Toml
+    pub fn set_road_suggestions(&mut self, path: &Path) {
+        self.road_suggestions = Some(path.to_path_buf());
+    }
 
-        debug!("CSV Fields: {fields}");
-        if field_count == 0 {
-            return Ok(0);
+    /// Write the corrected roads files and the road suggestions to the directory, instead of the directories in the Toml
+    pub fn set_corrected_roads_dir(&mut self, dir: &Path) {
+        for path in self
+            .corrected_roads
+            .values_mut()
+            .chain(self.road_suggestions.as_mut())
+        {
+            if let Some(file_name) = path.file_name() {
+                *path = dir.join(file_name);
+            }
         }
+    }
 
-        let mut insert_stmt = self
-            .connection()
-            .prepare(&format!("INSERT INTO {name} ({fields}) VALUES({values})"))?;
+    /// Choose whether the corrected roads are written, or only checked
+    pub fn set_corrections(&mut self, corrections: Corrections) {
+        self.corrections = corrections;
+    }
 
-        // collect all the data
-        let mut count = 0;
-        for record in csv.into_records() {
-            let record = match record {
-                Ok(record) => record,
-                Err(err) => {
-                    warn!("Error parsing CSV record: {err}",);
-                    continue;
-                }
-            };
+    /// Fail the build if any rows have codes that aren't in the tables their FOREIGN KEYs reference,
+    ///   otherwise they are only reported, see NewDB::check_foreign_keys
+    pub fn set_strict_foreign_keys(&mut self, strict: bool) {
+        self.strict_foreign_keys = strict;
+    }
 
-            // convert empty strings to NULL, should we change '-' to NULL as well?
-            let record_iter = record
-                .iter()
-                .map(|s| if s.is_empty() { None } else { Some(s) });
-
-            insert_stmt
-                .insert(params_from_iter(record_iter))
-                .inspect(|count| {
-                    if report_new_entries && *count > 0 {
-                        if log_enabled!(Level::Debug) {
-                            use std::fmt::Write;
-                            let mut fields = String::new();
-                            for (field, value) in headers_record.iter().zip(record.iter()) {
-                                write!(&mut fields, "{field}={value},")
-                                    .expect("failed to write to string");
-                            }
-                            debug!("INSERTED {fields}");
-                        }
-                    }
-                })
-                .or_else(|result| {
-                    use rusqlite::ffi;
-                    use rusqlite::Error::SqliteFailure;
-                    use rusqlite::ErrorCode::ConstraintViolation;
-
-                    // if we're allowing dups, ignore the error
-                    //  TODO: this should probably check for the correct error
-                    if allow_duplicates
-                        && matches!(
-                            result,
-                            SqliteFailure(
-                                ffi::Error {
-                                    code: ConstraintViolation,
-                                    ..
-                                },
-                                ..
-                            )
-                        )
-                    {
-                        Ok(0)
-                    } else {
-                        Err(result)
-                    }
-                })
-                .inspect_err(|e| {
-                    if log_enabled!(Level::Error) {
-                        use std::fmt::Write;
-                        let mut error_message = String::new();
-
-                        for (field, value) in headers_record.iter().zip(record.iter()) {
-                            write!(error_message, "{field}={value},")
-                                .expect("Failed to write to error message");
-                        }
+    /// The canonical spellings of the street types and numbered streets the roads are normalized with, read from the
+    ///   files in [road-abbreviations], without them the roads are only parsed
+    pub fn road_abbreviations(&self) -> Result<RoadAbbreviations, SchemaError> {
+        let mut abbreviations = RoadAbbreviations::default();
+        let RoadAbbreviationFiles {
+            street_types,
+            numbered_streets,
+        } = &self.road_abbreviations;
 
-                        error!("error on insert into {name}: {e}, row {count}:");
-                    }
-                })?;
+        if let Some(path) = street_types {
+            for record in read_abbreviations(path, RoadAbbreviationFiles::STREET_TYPES_COLUMNS)? {
+                abbreviations.add_street_type(&record[0], &record[1]);
+            }
+        }
+        if let Some(path) = numbered_streets {
+            for record in read_abbreviations(path, RoadAbbreviationFiles::NUMBERED_STREETS_COLUMNS)?
+            {
+                abbreviations.add_numbered_street(&record[0], &record[1], &record[2]);
+            }
+        }
 
-            count += 1;
+        Ok(abbreviations)
+    }
+
+    /// The file the corrected roads for the table are written to
+    fn corrected_roads_csv(&self, corrected: &str) -> Result<PathBuf, SchemaError> {
+        self.corrected_roads
+            .get(corrected)
+            .cloned()
+            .ok_or_else(|| SchemaError::MissingTable {
+                table: corrected.to_string(),
+                section: "corrected-roads",
+            })
+    }
+
+    /// The checked in data of the lookup table, e.g. the corrected roads
+    pub(crate) fn lookup_table_data(&self, table: &str) -> Result<PathBuf, SchemaError> {
+        self.lookup_tables
+            .get(table)
+            .map(|lookup_table| lookup_table.data.clone())
+            .ok_or_else(|| SchemaError::MissingTable {
+                table: table.to_string(),
+                section: "lookup-tables",
+            })
+    }
+
+    /// The primary tables in the order they are loaded, each after all the tables it depends on,
+    ///   tables that don't depend on each other are loaded in the order of their names
+    pub fn table_order(&self) -> Result<Vec<&str>, SchemaError> {
+        for (table_name, table) in self.tables.iter().collect::<BTreeMap<_, _>>() {
+            let dependencies = table.dependencies(table_name)?;
+            if let Some(missing) = dependencies
+                .into_iter()
+                .find(|d| !self.tables.contains_key(d) && !self.lookup_tables.contains_key(d))
+            {
+                return Err(SchemaError::MissingTable {
+                    table: missing,
+                    section: "tables",
+                });
+            }
         }
 
-        info!("INSERTED {count} records");
-        Ok(count)
+        topological_order(&self.dependency_graph())
     }
 
-    /// Initialize all the lookup tables in lookup_tables
-    fn init_lookup_tables(
+    /// The primary tables each primary table depends on, all the lookup tables are loaded before them,
+    ///   and the schemas that can't be read have no dependencies
+    fn dependency_graph(&self) -> BTreeMap<&str, BTreeSet<String>> {
+        self.tables
+            .iter()
+            .map(|(table_name, table)| {
+                let mut dependencies = table.dependencies(table_name).unwrap_or_default();
+                dependencies.retain(|dependency| self.tables.contains_key(dependency));
+                (table_name.as_str(), dependencies)
+            })
+            .collect()
+    }
+}
+
+impl Schema {
+    /// Check the Schemas TOML before any data is loaded, fails with all the problems found, see Schema::problems
+    pub fn validate(
         &self,
-        lookup_tables: &HashMap<String, LookupTable>,
-        table_schema: &Path,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        for (name, table) in lookup_tables {
-            info!("LOADING {name}");
-            let schema = table.schema.as_deref().unwrap_or(table_schema);
-            self.create_table(name, &table.pk_type, schema)?;
-            self.load_data(name, &table.data)?;
+        old_switrs_path: Option<&Path>,
+        ccrs_data_path: &Path,
+    ) -> Result<(), SchemaError> {
+        let problems = self.problems(old_switrs_path, ccrs_data_path);
+        if problems.is_empty() {
+            return Ok(());
         }
-
-        Ok(())
+        Err(SchemaError::Invalid(problems))
     }
 
-    /// Create and load all the tables defined in the Schema
-    fn load_from_schema(
+    /// All the mistakes in the Schemas TOML that would fail the build part way through, or silently skip a table
+    ///
+    /// The tables must only depend on tables in the Toml, without cycles, all schema and data files must exist,
+    ///   the schema templates must only use known parameters, and the ids of the lookup table data must fit their
+    ///   pk_type. The legacy SWITRS data is only checked with an old_switrs_path.
+    pub fn problems(
         &self,
-        schemas: &Schema,
-        old_switrs_path: &Path,
+        old_switrs_path: Option<&Path>,
         ccrs_data_path: &Path,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        // initialize lookup tables
-        self.connection()
-            .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)?;
+    ) -> Vec<SchemaProblem> {
+        let mut problems = Vec::new();
 
-        // Build all the standard tables
-        for table_name in &schemas.table_order {
-            let table: &PrimaryTable = schemas
-                .tables
-                .get(table_name)
-                .ok_or_else(|| format!("table missing from [tables]: {table_name}"))?;
+        // primary tables, in order so the problems are stable
+        let mut table_names = self.tables.keys().collect::<Vec<_>>();
+        table_names.sort();
+        for table_name in table_names {
+            let table = &self.tables[table_name];
+            let problem = |problem| SchemaProblem::new("tables", table_name, problem);
+
+            // a schema that can't be read is a template problem
+            for dependency in table.dependencies(table_name).unwrap_or_default() {
+                if !self.tables.contains_key(&dependency)
+                    && !self.lookup_tables.contains_key(&dependency)
+                {
+                    problems.push(problem(format!(
+                        "depends on {dependency}, which is not in [tables] or [lookup-tables]"
+                    )));
+                }
+            }
+
+            if let Some(template) = self.template_problem(&table.schema, table_name, "") {
+                problems.push(problem(template));
+            }
 
             match &table.data {
                 DataPath::CcrsData(regex) => {
-                    self.load_ccrs_csvs(table, table_name, ccrs_data_path, &regex)?
+                    if let Err(e) = Regex::new(regex) {
+                        problems.push(problem(format!("bad file name pattern {regex}: {e}")));
+                    }
+                    if !ccrs_data_path.is_dir() {
+                        problems.push(problem(format!(
+                            "ccrs data directory not found: {}",
+                            ccrs_data_path.display()
+                        )));
+                    }
                 }
                 DataPath::RawData(path) => {
-                    self.load_from_csv(table, table_name, Some(old_switrs_path.join(path)))?
+                    if let Some(data) = old_switrs_path.map(|dir| dir.join(path)) {
+                        if !data.is_file() {
+                            problems.push(problem(format!("data not found: {}", data.display())));
+                        }
+                    }
                 }
-                DataPath::Path(path) => {
-                    self.load_from_csv(table, table_name, Some(path.clone()))?
+                DataPath::Path(data) => {
+                    if let Err(e) = resources::open(data) {
+                        problems.push(problem(format!(
+                            "failed to read data {}: {e}",
+                            data.display()
+                        )));
+                    }
                 }
-                DataPath::Empty => self.load_from_csv(table, table_name, None)?,
-            };
+                DataPath::Empty => (),
+            }
         }
 
-        // build fixup tables
-        self.fixup_tables()?;
-
-        Ok(())
-    }
-
-    /// Load CCRS data tables
-    fn load_ccrs_csvs(
-        &self,
-        table: &PrimaryTable,
-        table_name: &str,
-        ccrs_data_path: &Path,
-        regex_name: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        info!("LOADING CCRS Table: {table_name}");
-        let regex = Regex::new(regex_name)?;
+        // lookup tables
+        let mut table_names = self.lookup_tables.keys().collect::<Vec<_>>();
+        table_names.sort();
+        for table_name in table_names {
+            let table = &self.lookup_tables[table_name];
+            let problem = |problem| SchemaProblem::new("lookup-tables", table_name, problem);
 
-        //
-        // load ccrs data
-        if !ccrs_data_path.is_dir() {
-            return Err(Box::<dyn std::error::Error>::from(format!(
-                "ccrs data directory not found"
-            )));
+            let schema = table.schema.as_deref().unwrap_or(&self.lookup_schema);
+            if let Some(template) = self.template_problem(schema, table_name, &table.pk_type) {
+                problems.push(problem(template));
+            }
+            for reference in schema_references(schema, table_name).unwrap_or_default() {
+                if !self.lookup_tables.contains_key(&reference) {
+                    problems.push(problem(format!(
+                        "references {reference}, which is not in [lookup-tables], they are loaded before the other tables"
+                    )));
+                }
+            }
+            if let Some(pk_type) = table.pk_type_problem() {
+                problems.push(problem(pk_type));
+            }
         }
 
-        let mut paths = Vec::<PathBuf>::new();
-        let dir = fs::read_dir(ccrs_data_path)?;
-        for entry in dir {
-            let entry = entry?;
-            if !entry.file_type()?.is_file() {
-                continue;
+        // the missing tables are problems above, only cycles are left
+        let lookup_order = lookup_table_order(&self.lookup_tables, &self.lookup_schema).map(|_| ());
+        for (section, order) in [
+            (
+                "tables",
+                topological_order(&self.dependency_graph()).map(|_| ()),
+            ),
+            ("lookup-tables", lookup_order),
+        ] {
+            if let Err(SchemaError::DependencyCycle(cycle)) = order {
+                problems.push(SchemaProblem::new(
+                    section,
+                    &cycle[0],
+                    format!("dependency cycle: {}", cycle.join(" -> ")),
+                ));
             }
+        }
 
-            let path = entry.path();
-            let file_name = path
-                .file_name()
-                .ok_or(format!("not a file: {}", path.display()))?
-                .to_string_lossy();
-
-            if regex.is_match(&file_name) {
-                paths.push(path);
+        // corrected roads, written by fixup_tables
+        for RoadTables { corrected, .. } in [RoadTables::SWITRS, RoadTables::CCRS] {
+            if !self.corrected_roads.contains_key(corrected) {
+                problems.push(SchemaProblem::new(
+                    "corrected-roads",
+                    corrected,
+                    "missing, the corrected roads can't be written",
+                ));
+            }
+            if !self.lookup_tables.contains_key(corrected) {
+                problems.push(SchemaProblem::new(
+                    "lookup-tables",
+                    corrected,
+                    "missing, the corrected roads can't be loaded",
+                ));
             }
         }
 
-        for path in paths {
-            self.load_from_csv(table, table_name, Some(path))?;
+        // road abbreviations, read by fixup_tables
+        let mut files = self.road_abbreviations.files();
+        files.sort_by_key(|(name, ..)| *name);
+        for (name, path, columns) in files {
+            if let Err(e) = read_abbreviations(path, columns) {
+                problems.push(SchemaProblem::new(
+                    "road-abbreviations",
+                    name,
+                    e.to_string(),
+                ));
+            }
         }
 
-        Ok(())
+        problems
     }
 
-    /// insert_from_csvs
-    fn load_from_csv(
-        &self,
-        table: &PrimaryTable,
-        table_name: &str,
-        data_path: Option<PathBuf>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        info!("LOADING {table_name} from {data_path:?}");
-        self.connection()
-            .create_table(table_name, "", &table.schema)?;
-
-        if let Some(data) = data_path {
-            self.connection().load_data(table_name, &data)?;
-        }
+    /// The problem with the schema template of the table, if it can't be read or has unknown parameters
+    fn template_problem(&self, schema: &Path, table_name: &str, pk_type: &str) -> Option<String> {
+        let ddl = match resources::read_to_string(schema) {
+            Ok(ddl) => ddl,
+            Err(e) => return Some(format!("failed to read schema {}: {e}", schema.display())),
+        };
 
-        Ok(())
+        let mut params = self.jurisdiction.template_params();
+        params.insert("table", table_name.to_string());
+        params.insert("pk_type", pk_type.to_string());
+        Template::new(ddl)
+            .render(&params)
+            .err()
+            .map(|e| format!("bad template in {}: {e}", schema.display()))
     }
+}
 
-    /// Run tasks to fill fixup tables, or produce csv's which add lookup tables to cleanup data
-    fn fixup_tables(&self) -> Result<(), Box<dyn std::error::Error>> {
-        self.fixup_roads()?;
-
-        Ok(())
+/// The lookup tables in the order they are loaded, each after the lookup tables referenced by its schema,
+///   otherwise in the order of their names
+fn lookup_table_order<'a>(
+    lookup_tables: &'a HashMap<String, LookupTable>,
+    lookup_schema: &Path,
+) -> Result<Vec<&'a str>, SchemaError> {
+    let mut dependencies = BTreeMap::new();
+    for (table_name, table) in lookup_tables {
+        let schema = table.schema.as_deref().unwrap_or(lookup_schema);
+        dependencies.insert(table_name.as_str(), schema_references(schema, table_name)?);
     }
 
-    /// This uses the Berkeley Road Typos and the Corrected Roads to construct a lookup table with correct road names
-    ///   for each Case ID
-    fn fixup_roads(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // when processing collision data, we will cleanup some data,
-        //   for that we have some custom insert and one off tables
-        let mut insert_road_stmt = self.connection().prepare(
-            "INSERT INTO switrs_normalized_roads (
-                case_id,
-                primary_rd,
-                primary_rd_address,
-                primary_rd_block,
-                primary_rd_direction,
-                secondary_rd,
-                secondary_rd_address,
-                secondary_rd_block,
-                seconardy_rd_direction
-            ) VALUES(
-                ?,
-                ?,
-                ?,
-                ?,
-                ?,
-                ?,
-                ?,
-                ?,
-                ?
-            )",
-        )?;
+    topological_order(&dependencies)
+}
 
-        let mut select_roads = self
-            .connection()
-            .prepare("SELECT case_id, primary_rd, secondary_rd FROM switrs_collisions")?;
+/// The tables referenced by FOREIGN KEYs in the schema, other than the tables it creates itself
+fn schema_references(schema: &Path, table_name: &str) -> Result<BTreeSet<String>, SchemaError> {
+    static REFERENCES: OnceLock<Regex> = OnceLock::new();
+    static CREATES: OnceLock<Regex> = OnceLock::new();
+    let references =
+        REFERENCES.get_or_init(|| Regex::new(r#"(?i)\bREFERENCES\s+"?(\w+)"#).expect("bad regex"));
+    let creates = CREATES.get_or_init(|| {
+        Regex::new(r#"(?i)\bCREATE\s+TABLE\s+(?:IF\s+NOT\s+EXISTS\s+)?"?(\w+)"#).expect("bad regex")
+    });
 
-        let mut roads = select_roads.query([])?;
-        while let Some(road) = roads.next()? {
-            // add normalized roads from the collisions table
-            let case_id = road.get_ref("case_id")?.as_str()?;
-            let primary_rd = road.get_ref("primary_rd")?.as_str()?;
-            let secondary_rd = road.get_ref("secondary_rd")?.as_str()?;
+    let ddl = resources::read_to_string(schema).for_path(schema)?;
+    let created = creates
+        .captures_iter(&ddl)
+        .map(|c| c[1].to_string())
+        .collect::<HashSet<_>>();
 
-            let primary_rd = normalize_road(primary_rd);
-            let secondary_rd = normalize_road(secondary_rd);
+    Ok(references
+        .captures_iter(&ddl)
+        .map(|c| c[1].to_string())
+        .filter(|table| table != table_name && !created.contains(table))
+        .collect())
+}
 
-            insert_road_stmt.insert([
-                Some(case_id),
-                Some(&primary_rd.road),
-                primary_rd.address,
-                primary_rd.block,
-                primary_rd.direction,
-                Some(&secondary_rd.road),
-                secondary_rd.address,
-                secondary_rd.block,
-                secondary_rd.direction,
-            ])
-            .inspect_err(|e| {
-                error!("error on insert into normalized_roadcase_id={case_id},primary={primary_rd:?},secondary={secondary_rd:?}: {e}");
-            })?;
-        }
+/// Sort the tables so each comes after the tables it depends on, dependencies that aren't in the map are already
+///   loaded, the first table by name that is ready is always next so the order doesn't change between builds
+fn topological_order<'a>(
+    dependencies: &BTreeMap<&'a str, BTreeSet<String>>,
+) -> Result<Vec<&'a str>, SchemaError> {
+    let mut order = Vec::with_capacity(dependencies.len());
+    let mut loaded = HashSet::new();
+    let is_loaded = |loaded: &HashSet<&str>, table: &str| {
+        loaded.contains(table) || !dependencies.contains_key(table)
+    };
 
-        //
+    while order.len() < dependencies.len() {
+        let next = dependencies.iter().find(|(table, depends_on)| {
+            !loaded.contains(**table) && depends_on.iter().all(|d| is_loaded(&loaded, d))
+        });
+        let Some((&table, _)) = next else {
+            return Err(SchemaError::DependencyCycle(find_cycle(
+                dependencies,
+                &loaded,
+            )));
+        };
+
+        loaded.insert(table);
+        order.push(table);
+    }
+
+    Ok(order)
+}
+
+/// A cycle in the dependencies of the tables that aren't loaded, every one of them depends on another one
+fn find_cycle(
+    dependencies: &BTreeMap<&str, BTreeSet<String>>,
+    loaded: &HashSet<&str>,
+) -> Vec<String> {
+    let unloaded = |table: &&str| !loaded.contains(table);
+    let mut path = Vec::<&str>::new();
+    let mut table = dependencies.keys().copied().find(unloaded);
+    while let Some(next) = table {
+        if let Some(start) = path.iter().position(|t| *t == next) {
+            path.push(next);
+            return path[start..].iter().map(|t| t.to_string()).collect();
+        }
+        path.push(next);
+        table = dependencies[next]
+            .iter()
+            .filter_map(|d| dependencies.get_key_value(d.as_str()).map(|(k, _)| *k))
+            .find(unloaded);
+    }
+
+    path.into_iter().map(String::from).collect()
+}
+
+impl LookupTable {
+    /// The problem with the data of the lookup table, if it can't be read or its ids don't fit the pk_type,
+    ///   i.e. aren't INTEGERs, or are longer than a CHAR(n) or VARCHAR2(n)
+    fn pk_type_problem(&self) -> Option<String> {
+        let (kind, length) = match self.pk_type.split_once('(') {
+            Some((kind, length)) => (kind, length.trim_end_matches(')').trim().parse().ok()),
+            None => (self.pk_type.as_str(), None),
+        };
+        let fits = |id: &str| match kind.trim().to_ascii_uppercase().as_str() {
+            "INTEGER" => id.parse::<i64>().is_ok(),
+            "CHAR" | "VARCHAR" | "VARCHAR2" => {
+                length.is_none_or(|length| id.chars().count() <= length)
+            }
+            _ => true,
+        };
+
+        let mut reader = match resources::open(&self.data) {
+            Ok(data) => csv::Reader::from_reader(data),
+            Err(e) => return Some(format!("failed to read data {}: {e}", self.data.display())),
+        };
+        let mut misfits = 0;
+        let mut first_misfit = None;
+        for record in reader.records() {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => return Some(format!("failed to read data {}: {e}", self.data.display())),
+            };
+            let id = record.get(0).unwrap_or_default();
+            if !fits(id) {
+                misfits += 1;
+                first_misfit.get_or_insert_with(|| id.to_string());
+            }
+        }
+
+        first_misfit.map(|id| {
+            format!(
+                "{misfits} ids in {data} don't fit the pk_type {pk_type}, the first is '{id}'",
+                data = self.data.display(),
+                pk_type = self.pk_type,
+            )
+        })
+    }
+}
+
+/// What is done with the corrected road names found for each crash when building the DB
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum Corrections {
+    /// Write the corrected roads to the files in [corrected-roads] of the Schemas TOML
+    #[default]
+    Write,
+    /// Compare the corrected roads to the lookup table data without writing them, fails if they differ
+    Check,
+    /// Only correct the roads in the DB, without writing or checking the corrected roads and road suggestions
+    Skip,
+}
+
+/// Number of records loaded in each transaction, unless the batch-size is set in the Schemas Toml
+pub const DEFAULT_BATCH_SIZE: usize = 10_000;
+
+/// The CSV files of the road abbreviations, see RoadAbbreviations
+#[derive(Debug, Default, Deserialize)]
+struct RoadAbbreviationFiles {
+    /// Each spelling of a street type and its abbreviation, e.g. `AVENUE,AVE`
+    #[serde(default, alias = "street-types")]
+    street_types: Option<PathBuf>,
+    /// Each numbered street, its name and the street type it has when there is none, e.g. `6TH,SIXTH,ST`
+    #[serde(default, alias = "numbered-streets")]
+    numbered_streets: Option<PathBuf>,
+}
+
+impl RoadAbbreviationFiles {
+    const STREET_TYPES_COLUMNS: &'static [&'static str] = &["street_type", "abbreviation"];
+    const NUMBERED_STREETS_COLUMNS: &'static [&'static str] = &["number", "name", "street_type"];
+
+    /// The files by their name in the Toml, with their columns
+    fn files(&self) -> Vec<(&'static str, &Path, &'static [&'static str])> {
+        [
+            (
+                "street-types",
+                &self.street_types,
+                Self::STREET_TYPES_COLUMNS,
+            ),
+            (
+                "numbered-streets",
+                &self.numbered_streets,
+                Self::NUMBERED_STREETS_COLUMNS,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(name, path, columns)| path.as_deref().map(|path| (name, path, columns)))
+        .collect()
+    }
+
+    fn paths_mut(&mut self) -> impl Iterator<Item = &mut PathBuf> {
+        self.street_types
+            .iter_mut()
+            .chain(self.numbered_streets.iter_mut())
+    }
+}
+
+/// Read the records of a road abbreviations CSV, its headers must be the columns
+fn read_abbreviations(
+    path: &Path,
+    columns: &[&str],
+) -> Result<Vec<csv::StringRecord>, SchemaError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(resources::open(path).for_path(path)?);
+
+    let headers = reader.headers().for_path(path)?;
+    if headers.iter().ne(columns.iter().copied()) {
+        return Err(SchemaError::Headers {
+            table: String::from("road-abbreviations"),
+            path: path.to_path_buf(),
+            message: format!("expected the columns {}", columns.join(",")),
+        });
+    }
+
+    reader
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .for_path(path)
+}
+
+/// The cities and counties to build the DB for, if empty all of California is included
+#[derive(Debug, Default, Deserialize)]
+pub struct Jurisdiction {
+    /// City names, like "Berkeley", or cnty_city_loc codes, like "0102" (see lookup-tables/CNTY_CITY_LOC.csv)
+    #[serde(default)]
+    include: Vec<String>,
+    /// County names, like "Alameda", all cities in the county are included
+    #[serde(default)]
+    counties: Vec<String>,
+}
+
+impl Jurisdiction {
+    /// Create a jurisdiction from city names or cnty_city_loc codes, and county names
+    pub fn new(include: Vec<String>, counties: Vec<String>) -> Self {
+        Self { include, counties }
+    }
+
+    /// True if no cities or counties are listed, i.e. all of California
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.counties.is_empty()
+    }
+
+    /// Query for all the ids in the cnty_city_loc table that are part of the jurisdiction
+    fn cnty_city_loc_query(&self, column: &str) -> String {
+        fn sql_list<'a>(values: impl Iterator<Item = &'a String>) -> String {
+            values
+                .map(|v| format!("'{}'", v.replace('\'', "''")))
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+
+        let (codes, cities): (Vec<_>, Vec<_>) = self
+            .include
+            .iter()
+            .partition(|s| s.chars().all(|c| c.is_ascii_digit()));
+
+        format!(
+            "SELECT {column} FROM cnty_city_loc WHERE id IN ({codes}) OR city COLLATE NOCASE IN ({cities}) OR county COLLATE NOCASE IN ({counties})",
+            codes = sql_list(codes.into_iter()),
+            cities = sql_list(cities.into_iter()),
+            counties = sql_list(self.counties.iter()),
+        )
+    }
+
+    /// Parameters for the table schema templates,
+    ///   {switrs_jurisdiction} and {ccrs_jurisdiction} are conditions on the cnty_city_loc and city_name columns
+    fn template_params(&self) -> HashMap<&'static str, String> {
+        let mut params = HashMap::new();
+        if self.is_empty() {
+            params.insert("switrs_jurisdiction", String::from("1"));
+            params.insert("ccrs_jurisdiction", String::from("1"));
+        } else {
+            params.insert(
+                "switrs_jurisdiction",
+                format!("cnty_city_loc IN ({})", self.cnty_city_loc_query("id")),
+            );
+            params.insert(
+                "ccrs_jurisdiction",
+                format!(
+                    "city_name COLLATE NOCASE IN ({})",
+                    self.cnty_city_loc_query("city")
+                ),
+            );
+        }
+
+        params
+    }
+}
+
+/// How rows from the CSV that conflict with existing rows (same primary key) are handled
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OnConflict {
+    /// Fail the load on the first conflicting row
+    Fail,
+    /// Keep the existing row, and skip the one from the CSV
+    Ignore,
+    /// Replace the existing row with the one from the CSV,
+    ///   if the table has a report_version, only when the CSV row is not an older version
+    Upsert,
+}
+
+/// Options for loading a CSV file into a table
+#[derive(Clone, Copy, Debug)]
+pub struct LoadOptions<'a> {
+    /// How rows that conflict with existing rows are handled
+    pub on_conflict: OnConflict,
+    /// Log each new row inserted, at debug level
+    pub report_new_entries: bool,
+    /// CSV headers that have been renamed, mapped to their column in the table
+    pub header_aliases: Option<&'a HashMap<String, String>>,
+    /// Number of records loaded in each transaction, 0 loads the whole file in one transaction
+    pub batch_size: usize,
+    /// Only load the records that match the filter
+    pub filter: Option<&'a RowFilter>,
+    /// Release of the CCRS file, stored in the ccrs_release column of each row
+    pub release: Option<&'a str>,
+    /// The parent of the rows, with a release the rows are only loaded if their parent row is from the same release
+    pub parent: Option<&'a ParentTable>,
+}
+
+impl Default for LoadOptions<'_> {
+    fn default() -> Self {
+        Self {
+            on_conflict: OnConflict::Ignore,
+            report_new_entries: false,
+            header_aliases: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            filter: None,
+            release: None,
+            parent: None,
+        }
+    }
+}
+
+/// Records are only loaded if the value of the column is one of the values, ignoring case
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RowFilter {
+    /// The column to check
+    pub column: String,
+    /// Lowercase values to keep
+    pub values: HashSet<String>,
+}
+
+impl RowFilter {
+    /// Create a filter for the column, keeping any of the values
+    pub fn new(column: &str, values: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            column: column.to_string(),
+            values: values.into_iter().map(|v| v.to_lowercase()).collect(),
+        }
+    }
+
+    /// True if the record should be loaded
+    fn matches(&self, value: &str) -> bool {
+        self.values.contains(&value.to_lowercase())
+    }
+}
+
+/// Commits the records loaded from a CSV in batches, the records of an unfinished batch are rolled back
+///   when dropped, e.g. on an error
+struct BatchTransaction<'c> {
+    connection: &'c Connection,
+    size: usize,
+    pending: usize,
+    open: bool,
+}
+
+impl<'c> BatchTransaction<'c> {
+    /// Start the first batch, a savepoint is used so the load can be part of a larger transaction
+    fn begin(connection: &'c Connection, size: usize) -> rusqlite::Result<Self> {
+        connection.execute_batch("SAVEPOINT load_data")?;
+        Ok(Self {
+            connection,
+            size,
+            pending: 0,
+            open: true,
+        })
+    }
+
+    /// Count a record, committing the batch when it is full
+    fn record(&mut self) -> rusqlite::Result<()> {
+        self.pending += 1;
+        if self.size > 0 && self.pending >= self.size {
+            self.connection
+                .execute_batch("RELEASE load_data; SAVEPOINT load_data")?;
+            self.pending = 0;
+        }
+
+        Ok(())
+    }
+
+    /// Commit the last batch
+    fn commit(mut self) -> rusqlite::Result<()> {
+        self.open = false;
+        self.connection.execute_batch("RELEASE load_data")
+    }
+}
+
+impl Drop for BatchTransaction<'_> {
+    fn drop(&mut self) {
+        if self.open {
+            if let Err(e) = self
+                .connection
+                .execute_batch("ROLLBACK TO load_data; RELEASE load_data")
+            {
+                error!("failed to rollback the load: {e}");
+            }
+        }
+    }
+}
+
+/// How the headers of a CSV file match up with the columns of the table it is loaded into
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct HeaderReconciliation {
+    /// Column for each header of the CSV, in order, None for the extra headers
+    pub fields: Vec<Option<String>>,
+    /// Columns of the table that are not in the CSV, these are left NULL
+    pub missing: Vec<String>,
+    /// Headers of the CSV without a column in the table
+    pub extra: Vec<String>,
+    /// Extra headers that look like a renamed missing column, as (header, column)
+    pub renamed: Vec<(String, String)>,
+}
+
+impl HeaderReconciliation {
+    /// Match the CSV headers to the table columns, headers are converted to snake_case or mapped with the aliases
+    pub fn new(
+        headers: &csv::StringRecord,
+        columns: &[String],
+        header_aliases: Option<&HashMap<String, String>>,
+    ) -> Self {
+        let alias = |header: &str| -> Option<&String> {
+            header_aliases?
+                .iter()
+                .find(|(from, _)| {
+                    from.as_str() == header || from.to_snake_case() == header.to_snake_case()
+                })
+                .map(|(_, to)| to)
+        };
+        let column = |field: &str| -> Option<&String> {
+            columns.iter().find(|c| c.eq_ignore_ascii_case(field))
+        };
+
+        let mut reconciled = Self::default();
+        for header in headers {
+            let field = alias(header)
+                .cloned()
+                .unwrap_or_else(|| header.to_snake_case());
+
+            // each column can only be loaded once
+            match column(&field) {
+                Some(c) if !reconciled.fields.contains(&Some(c.clone())) => {
+                    reconciled.fields.push(Some(c.clone()))
+                }
+                _ => {
+                    reconciled.fields.push(None);
+                    reconciled.extra.push(header.to_string());
+                }
+            }
+        }
+
+        reconciled.missing = columns
+            .iter()
+            .filter(|c| !reconciled.fields.contains(&Some((*c).clone())))
+            .cloned()
+            .collect();
+
+        // a rename is often only a change in spacing, the order of words, or a word added or removed
+        let squash = |s: &str| s.to_snake_case().replace('_', "");
+        let words = |s: &str| {
+            let mut words = s
+                .to_snake_case()
+                .split('_')
+                .map(String::from)
+                .collect::<Vec<_>>();
+            words.sort();
+            words
+        };
+        for header in &reconciled.extra {
+            let header_squashed = squash(header);
+            if let Some(missing) = reconciled.missing.iter().find(|m| {
+                let missing_squashed = squash(m);
+                words(header) == words(m)
+                    || (header_squashed.len().min(missing_squashed.len()) >= 4
+                        && (header_squashed.contains(&missing_squashed)
+                            || missing_squashed.contains(&header_squashed)))
+            }) {
+                reconciled.renamed.push((header.clone(), missing.clone()));
+            }
+        }
+
+        reconciled
+    }
+
+    /// Log the missing columns, and fail if there are any headers that can't be loaded
+    fn check(&self, name: &str, table_data: &Path) -> Result<(), SchemaError> {
+        if !self.missing.is_empty() {
+            info!(
+                "MISSING columns of {name} in {table_data}, these will be NULL: {missing}",
+                table_data = table_data.display(),
+                missing = self.missing.join(", ")
+            );
+        }
+
+        if self.extra.is_empty() {
+            return Ok(());
+        }
+
+        let mut message = format!("no column for {extra}", extra = self.extra.join(", "));
+        for (header, column) in &self.renamed {
+            message.push_str(&format!(
+                "; '{header}' may be a rename of {column}, add `\"{header}\" = \"{column}\"` to the header-aliases of {name} in the Schemas TOML"
+            ));
+        }
+        Err(SchemaError::Headers {
+            table: name.to_string(),
+            path: table_data.to_path_buf(),
+            message,
+        })
+    }
+}
+
+/// Number of chunks of records parsed from a file that can wait to be inserted, see parse_in_order
+const PARSED_CHUNKS: usize = 2;
+
+/// The files parsed by parse_in_order, the workers take the next one once it is within the window
+struct ParseQueue<P, R> {
+    /// Files not yet parsed, in order, with the channel their records are sent over
+    files: std::vec::IntoIter<(P, SyncSender<Vec<R>>)>,
+    /// Index of the next file to parse
+    next: usize,
+    /// Number of files inserted so far
+    inserted: usize,
+    /// Set when the inserts are done or failed, or a worker panicked, so the workers stop
+    stopped: bool,
+}
+
+/// Stops the workers of parse_in_order, and drops the files left, when the inserts are done or fail, or a worker stops
+///
+/// A worker only stops before all the files are parsed if the inserts are done, or it panicked, and then dropping the
+///   channels of the files left ends their records, so the inserts don't wait for them.
+struct StopParsing<'q, P, R>(&'q (Mutex<ParseQueue<P, R>>, Condvar));
+
+impl<P, R> Drop for StopParsing<'_, P, R> {
+    fn drop(&mut self) {
+        let (queue, window) = self.0;
+        let mut queue = queue.lock().unwrap_or_else(PoisonError::into_inner);
+        queue.stopped = true;
+        queue.files = Vec::new().into_iter();
+        window.notify_all();
+    }
+}
+
+/// Parse the files on up to `workers` threads, and insert their records in the order of the files on this thread
+///
+/// The records of each file are sent in chunks of `chunk_size` over a bounded channel, so a worker waits while the
+///   inserts are behind, and at most `workers` files are parsed ahead of the one being inserted. Only a few chunks of
+///   each of those files are held in memory, and the result is the same as loading the files one after another.
+fn parse_in_order<P: Send, I, R: Send, E>(
+    files: Vec<P>,
+    workers: usize,
+    chunk_size: usize,
+    parse: impl Fn(P) -> I + Sync,
+    mut insert: impl FnMut(usize, iter::Flatten<mpsc::IntoIter<Vec<R>>>) -> Result<(), E>,
+) -> Result<(), E>
+where
+    I: Iterator<Item = R>,
+{
+    let count = files.len();
+    let workers = workers.clamp(1, count.max(1));
+    let chunk_size = chunk_size.max(1);
+    let (files, receivers): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .map(|file| {
+            let (sender, receiver) = mpsc::sync_channel(PARSED_CHUNKS);
+            ((file, sender), receiver)
+        })
+        .unzip();
+    let queue = (
+        Mutex::new(ParseQueue {
+            files: files.into_iter(),
+            next: 0,
+            inserted: 0,
+            stopped: false,
+        }),
+        Condvar::new(),
+    );
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let queue = &queue;
+            let parse = &parse;
+            scope.spawn(move || {
+                let _stop = StopParsing(queue);
+                let (queue, window) = queue;
+                loop {
+                    let (file, sender) = {
+                        let mut queue = queue.lock().unwrap_or_else(PoisonError::into_inner);
+                        while !queue.stopped
+                            && queue.next < count
+                            && queue.next >= queue.inserted + workers
+                        {
+                            queue = window.wait(queue).unwrap_or_else(PoisonError::into_inner);
+                        }
+                        if queue.stopped {
+                            return;
+                        }
+                        let Some(file) = queue.files.next() else {
+                            return;
+                        };
+                        queue.next += 1;
+                        file
+                    };
+
+                    // the writer hangs up when it's done or fails
+                    let mut chunk = Vec::with_capacity(chunk_size);
+                    for record in parse(file) {
+                        chunk.push(record);
+                        if chunk.len() == chunk_size {
+                            let full = mem::replace(&mut chunk, Vec::with_capacity(chunk_size));
+                            if sender.send(full).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    if !chunk.is_empty() && sender.send(chunk).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        // the records of a file only end early if a worker panicked, which the scope passes on
+        let stop = StopParsing(&queue);
+        for (index, receiver) in receivers.into_iter().enumerate() {
+            insert(index, receiver.into_iter().flatten())?;
+
+            let (queue, window) = stop.0;
+            queue
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .inserted += 1;
+            window.notify_all();
+        }
+
+        Ok(())
+    })
+}
+
+/// Reader for a CSV file, from disk or the embedded resources
+type CsvReader = csv::Reader<DigestReader>;
+
+/// The size and SHA-256 of a file, once it has been read to the end
+type FileDigest = Arc<OnceLock<(u64, String)>>;
+
+/// Computes the size and SHA-256 of a file while it is read, so the build_manifest records the file as it was loaded
+struct DigestReader {
+    inner: Box<dyn Read + Send>,
+    sha256: Sha256,
+    size: u64,
+    digest: FileDigest,
+}
+
+impl Read for DigestReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read == 0 {
+            let sha256 = format!("{:x}", self.sha256.finalize_reset());
+            // only the first end of the file is recorded
+            let _ = self.digest.set((self.size, sha256));
+        } else {
+            self.sha256.update(&buf[..read]);
+            self.size += read as u64;
+        }
+
+        Ok(read)
+    }
+}
+
+/// The headers of a CSV file opened for loading, matched to the columns of the table
+struct CsvHeaders<'a> {
+    /// The headers as they are in the CSV file
+    record: csv::StringRecord,
+    /// The columns of the table for the headers
+    reconciled: HeaderReconciliation,
+    /// The filter for the records, with the index of its column
+    filter: Option<(usize, &'a RowFilter)>,
+    /// The size and SHA-256 of the file, set once the reader is at its end
+    digest: FileDigest,
+}
+
+/// A record parsed from a CSV file
+enum ParsedRecord {
+    /// A record to insert into the table
+    Record(csv::StringRecord),
+    /// A record that didn't match the filter
+    Filtered,
+    /// A record that couldn't be parsed
+    Invalid(csv::Error),
+}
+
+impl<'a> CsvHeaders<'a> {
+    /// Open the CSV file and match the headers to the columns of the table, the reader is left at the first record
+    fn open(
+        name: &str,
+        table_data: &Path,
+        columns: &[String],
+        header_aliases: Option<&HashMap<String, String>>,
+        filter: Option<&'a RowFilter>,
+    ) -> Result<(Self, CsvReader), SchemaError> {
+        let digest = FileDigest::default();
+        let mut reader = csv::ReaderBuilder::new()
+            .quoting(true)
+            .has_headers(true)
+            .trim(csv::Trim::All)
+            .from_reader(DigestReader {
+                inner: resources::open(table_data).for_path(table_data)?,
+                sha256: Sha256::new(),
+                size: 0,
+                digest: Arc::clone(&digest),
+            });
+
+        let record = reader.headers().for_path(table_data)?.clone();
+        let reconciled = HeaderReconciliation::new(&record, columns, header_aliases);
+        reconciled.check(name, table_data)?;
+
+        // the filter is applied to the records, before they are inserted
+        let filter = filter
+            .map(|filter| {
+                reconciled
+                    .fields
+                    .iter()
+                    .position(|f| f.as_deref() == Some(filter.column.as_str()))
+                    .map(|index| (index, filter))
+                    .ok_or_else(|| SchemaError::Headers {
+                        table: name.to_string(),
+                        path: table_data.to_path_buf(),
+                        message: format!("no {column} to filter on", column = filter.column),
+                    })
+            })
+            .transpose()?;
+
+        Ok((
+            Self {
+                record,
+                reconciled,
+                filter,
+                digest,
+            },
+            reader,
+        ))
+    }
+
+    /// The size and SHA-256 of the file, after all its records have been parsed
+    fn digest(&self, table_data: &Path) -> Result<(u64, String), SchemaError> {
+        self.digest
+            .get()
+            .cloned()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the file was not read to the end",
+                )
+            })
+            .for_path(table_data)
+    }
+
+    /// Parse and filter all the records from the reader
+    fn parse<'r>(&'r self, reader: CsvReader) -> impl Iterator<Item = ParsedRecord> + 'r {
+        reader.into_records().map(|record| match record {
+            Ok(record) => match self.filter {
+                Some((index, filter)) if !filter.matches(record.get(index).unwrap_or_default()) => {
+                    ParsedRecord::Filtered
+                }
+                _ => ParsedRecord::Record(record),
+            },
+            Err(err) => ParsedRecord::Invalid(err),
+        })
+    }
+}
+
+/// Insert the parsed records from the CSV file at table_data into the named table
+fn insert_records(
+    connection: &Connection,
+    name: &str,
+    table_data: &Path,
+    headers: &CsvHeaders<'_>,
+    options: &LoadOptions<'_>,
+    records: impl Iterator<Item = ParsedRecord>,
+) -> Result<LoadStats, SchemaError> {
+    let LoadOptions {
+        on_conflict,
+        report_new_entries,
+        batch_size,
+        release,
+        parent,
+        ..
+    } = *options;
+
+    // build up the insert statement
+    let mut columns = headers
+        .reconciled
+        .fields
+        .iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let csv_columns = columns.len();
+    if release.is_some() {
+        columns.push(CCRS_RELEASE_COLUMN);
+    }
+
+    let fields = columns.join(", ");
+    let values = (1..=columns.len())
+        .map(|i| format!("?{i}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let updates = columns
+        .iter()
+        .map(|c| format!("{c} = excluded.{c}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    debug!("CSV Fields: {fields}");
+    if csv_columns == 0 {
+        return Ok(LoadStats::default());
+    }
+
+    // rows whose parent is from another release belong to an older version of it, e.g. the parties of a crash
+    //   that was replaced by a newer report version, or rejected as an older one, and are skipped
+    let parent_key = parent.zip(release).and_then(|(parent, _)| {
+        let key = columns.iter().position(|c| *c == parent.key)?;
+        Some((parent, key + 1, columns.len()))
+    });
+    let mut insert = match parent_key {
+        Some((ParentTable { table, key }, key_param, release_param)) => format!(
+            "INSERT INTO {name} ({fields}) SELECT {values} WHERE EXISTS (
+                SELECT 1 FROM {table} WHERE {table}.{key} = ?{key_param} AND {table}.{CCRS_RELEASE_COLUMN} = ?{release_param}
+            )"
+        ),
+        None => format!("INSERT INTO {name} ({fields}) VALUES({values})"),
+    };
+    if on_conflict == OnConflict::Upsert {
+        insert.push_str(&format!(" ON CONFLICT DO UPDATE SET {updates}"));
+
+        // never replace a newer version of a report with an older one
+        if columns.contains(&"report_version") {
+            insert.push_str(&format!(
+                " WHERE coalesce(CAST(excluded.report_version AS INTEGER), 0) >= coalesce({name}.report_version, 0)"
+            ));
+        }
+    }
+    let mut insert_stmt = connection.prepare(&insert).for_table(name)?;
+
+    // when nothing was inserted, this tells a missing parent apart from a newer version of the row
+    let mut parent_stmt = parent_key
+        .map(|(ParentTable { table, key }, _, _)| {
+            connection.prepare(&format!(
+                "SELECT EXISTS (SELECT 1 FROM {table} WHERE {key} = ?1 AND {CCRS_RELEASE_COLUMN} = ?2)"
+            ))
+        })
+        .transpose()
+        .for_table(name)?;
+
+    // collect all the data
+    let mut count = 0;
+    let mut older_versions = 0;
+    let mut without_parent = 0;
+    let mut rejected = 0;
+    let mut stats = LoadStats::default();
+    let mut batch = BatchTransaction::begin(connection, batch_size).for_table(name)?;
+    for record in records {
+        batch.record().for_table(name)?;
+        let record = match record {
+            ParsedRecord::Record(record) => record,
+            ParsedRecord::Filtered => {
+                stats.filtered += 1;
+                continue;
+            }
+            ParsedRecord::Invalid(err) => {
+                rejected += 1;
+                stats.reject(
+                    name,
+                    table_data,
+                    Rejection {
+                        line: err.position().map(csv::Position::line),
+                        record: None,
+                        kind: RejectionKind::Parse,
+                        message: err.to_string(),
+                    },
+                );
+                continue;
+            }
+        };
+
+        // convert empty strings to NULL, should we change '-' to NULL as well?
+        let record_iter = record
+            .iter()
+            .map(|s| if s.is_empty() { None } else { Some(s) })
+            .chain(release.map(Some));
+
+        let changed = match insert_stmt.execute(params_from_iter(record_iter)) {
+            Ok(changed) => changed,
+            Err(err) => {
+                // duplicates are only skipped when ignoring conflicts, other constraint violations are always rejected
+                let kind = match RejectionKind::of(&err) {
+                    Some(RejectionKind::Duplicate) if on_conflict != OnConflict::Ignore => None,
+                    kind => kind,
+                };
+
+                if let Some(kind) = kind {
+                    rejected += 1;
+                    stats.reject(
+                        name,
+                        table_data,
+                        Rejection {
+                            line: record.position().map(csv::Position::line),
+                            record: Some(raw_record(&record)),
+                            kind,
+                            message: err.to_string(),
+                        },
+                    );
+                    continue;
+                }
+
+                error!(
+                    "error on insert into {name}: {err}, row {count}: {record}",
+                    record = raw_record(&record)
+                );
+                return Err(SchemaError::Insert {
+                    table: name.to_string(),
+                    path: table_data.to_path_buf(),
+                    line: record
+                        .position()
+                        .map(csv::Position::line)
+                        .unwrap_or_default(),
+                    source: err,
+                });
+            }
+        };
+
+        if report_new_entries && changed > 0 && log_enabled!(Level::Debug) {
+            use std::fmt::Write;
+            let mut fields = String::new();
+            for (field, value) in headers.record.iter().zip(record.iter()) {
+                write!(&mut fields, "{field}={value},").expect("failed to write to string");
+            }
+            debug!("INSERTED {fields}");
+        }
+
+        // nothing is inserted when the row's parent isn't in the release, e.g. it was filtered out, otherwise the
+        //   upsert only leaves the row unchanged when the existing report is a newer version
+        if changed == 0 {
+            let has_parent = match (&mut parent_stmt, parent_key) {
+                (Some(parent_stmt), Some((_, key_param, _))) => parent_stmt
+                    .query_row(
+                        (record.get(key_param - 1).filter(|k| !k.is_empty()), release),
+                        |row| row.get(0),
+                    )
+                    .for_table(name)?,
+                _ => true,
+            };
+            if !has_parent {
+                without_parent += 1;
+            } else if on_conflict == OnConflict::Upsert {
+                older_versions += 1;
+            }
+        }
+        if changed > 0 {
+            stats.rows += 1;
+        } else {
+            stats.skipped += 1;
+        }
+        count += 1;
+    }
+
+    if older_versions > 0 {
+        info!("KEPT newer report versions over {older_versions} records");
+    }
+    if let Some((ParentTable { table, .. }, ..)) = parent_key.filter(|_| without_parent > 0) {
+        info!("SKIPPED {without_parent} records without their {table} in the same release");
+    }
+    if rejected > stats.rejections.len() {
+        warn!(
+            "RECORDING only the first {kept} of {rejected} rejected records in load_rejections",
+            kept = stats.rejections.len()
+        );
+    }
+
+    batch.commit().for_table(name)?;
+
+    if stats.filtered > 0 {
+        info!(
+            "FILTERED {filtered} records not matching the {column}",
+            filtered = stats.filtered,
+            column = headers
+                .filter
+                .map(|(_, f)| f.column.as_str())
+                .unwrap_or_default()
+        );
+    }
+    info!("INSERTED {count} records");
+    Ok(stats)
+}
+
+/// Table with the bookkeeping of the CCRS files imported into the DB, see schema/ccrs_imports.sql
+pub const CCRS_IMPORTS_TABLE: &str = "ccrs_imports";
+
+/// Table with the CCRS crashes marked as deleted, see schema/ccrs_deleted_crashes.sql
+pub const CCRS_DELETED_CRASHES_TABLE: &str = "ccrs_deleted_crashes";
+
+/// Column of the CCRS tables with the release of the file each row was loaded from, e.g. 2024 for Crashes_2024.csv
+pub const CCRS_RELEASE_COLUMN: &str = "ccrs_release";
+
+/// Table with the codes that aren't in the tables their FOREIGN KEYs reference, see schema/foreign_key_violations.sql
+pub const FOREIGN_KEY_VIOLATIONS_TABLE: &str = "foreign_key_violations";
+
+/// Rows with a code that isn't in the table their FOREIGN KEY references, usually a code missing from a lookup table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForeignKeyViolation {
+    /// The table with the rows
+    pub table: String,
+    /// The columns of the FOREIGN KEY, separated by commas
+    pub column: String,
+    /// The referenced table, e.g. weather
+    pub parent: String,
+    /// The code that isn't in the referenced table, the values of the columns separated by commas
+    pub code: String,
+    /// Number of rows with the code
+    pub rows: usize,
+}
+
+/// Why a record from a CSV file was not loaded into its table
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RejectionKind {
+    /// The record could not be parsed from the CSV
+    Parse,
+    /// A record with the same primary key, or unique columns, is already in the table
+    Duplicate,
+    /// The record violates another constraint on the table, e.g. NOT NULL
+    Constraint,
+}
+
+impl RejectionKind {
+    /// Name of the kind, as stored in the load_rejections table
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Parse => "parse",
+            Self::Duplicate => "duplicate",
+            Self::Constraint => "constraint",
+        }
+    }
+
+    /// The kind of rejection for an error from the insert, None if the error should fail the load
+    fn of(err: &rusqlite::Error) -> Option<Self> {
+        use rusqlite::ffi;
+
+        match err {
+            rusqlite::Error::SqliteFailure(
+                ffi::Error {
+                    code: rusqlite::ErrorCode::ConstraintViolation,
+                    extended_code,
+                },
+                ..,
+            ) => match *extended_code {
+                ffi::SQLITE_CONSTRAINT_PRIMARYKEY | ffi::SQLITE_CONSTRAINT_UNIQUE => {
+                    Some(Self::Duplicate)
+                }
+                _ => Some(Self::Constraint),
+            },
+            _ => None,
+        }
+    }
+}
+
+/// A record from a CSV file that was not loaded into its table, recorded in the load_rejections table
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rejection {
+    /// Line of the record in the CSV file
+    pub line: Option<u64>,
+    /// The record as it was in the CSV file, if it could be parsed
+    pub record: Option<String>,
+    /// Why the record was rejected
+    pub kind: RejectionKind,
+    /// The error from parsing or inserting the record
+    pub message: String,
+}
+
+/// Counts of the records from a CSV file loaded into a table
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LoadStats {
+    /// Records inserted or updated in the table
+    pub rows: usize,
+    /// Records not written, because of duplicates, older report versions, or a parent that isn't in the same release
+    pub skipped: usize,
+    /// Records that failed to parse or violated a constraint of the table
+    pub errored: usize,
+    /// Records not loaded because they didn't match the filter, e.g. outside of the jurisdiction
+    pub filtered: usize,
+    /// The records that were skipped as duplicates or errored, only the first MAX_REJECTIONS of them
+    pub rejections: Vec<Rejection>,
+}
+
+/// Most rejected records kept from each file for the load_rejections table, the rest are only counted and logged
+pub const MAX_REJECTIONS: usize = 1_000;
+
+impl LoadStats {
+    /// All the records in the CSV file
+    pub fn records(&self) -> usize {
+        self.rows + self.skipped + self.errored + self.filtered
+    }
+
+    /// Record a rejected record, duplicates are counted as skipped and all others as errored
+    fn reject(&mut self, table_name: &str, table_data: &Path, rejection: Rejection) {
+        // duplicates are expected, e.g. when reloading the corrected roads, so only errors are warnings
+        let level = if rejection.kind == RejectionKind::Duplicate {
+            self.skipped += 1;
+            Level::Debug
+        } else {
+            self.errored += 1;
+            Level::Warn
+        };
+
+        log!(
+            level,
+            "REJECTED {kind} record for {table_name} at {table_data}:{line}: {message}, record: {record}",
+            kind = rejection.kind.as_str(),
+            table_data = table_data.display(),
+            line = rejection.line.unwrap_or_default(),
+            message = rejection.message,
+            record = rejection.record.as_deref().unwrap_or_default(),
+        );
+        if self.rejections.len() < MAX_REJECTIONS {
+            self.rejections.push(rejection);
+        }
+    }
+}
+
+/// A data file loaded into the DB, recorded in the build_manifest_files table
+#[derive(Debug)]
+pub struct ImportedFile {
+    table_name: String,
+    path: PathBuf,
+    stats: LoadStats,
+    /// Size of the file in bytes, as it was loaded
+    file_size: u64,
+    /// SHA-256 of the file, as it was loaded
+    sha256: String,
+}
+
+/// Extensions to the DB Connection to initialize the DB
+pub trait NewDB {
+    /// Get access to the DB connection (generally will be Self)
+    fn connection(&self) -> &Connection;
+
+    /// Create a table where the name and pk_type are passed into the sql as template parameters
+    fn create_table(
+        &self,
+        name: &str,
+        pk_type: &str,
+        table_schema: &Path,
+    ) -> Result<(), SchemaError> {
+        self.create_table_for_jurisdiction(name, pk_type, table_schema, &Jurisdiction::default())
+    }
+
+    /// Create a table where the name, pk_type and jurisdiction conditions are passed into the sql as template parameters
+    fn create_table_for_jurisdiction(
+        &self,
+        name: &str,
+        pk_type: &str,
+        table_schema: &Path,
+        jurisdiction: &Jurisdiction,
+    ) -> Result<(), SchemaError> {
+        // build the DDL expression
+        let ddl = resources::read_to_string(table_schema).for_path(table_schema)?;
+        let ddl = Template::new(ddl);
+        let data = {
+            let mut map = jurisdiction.template_params();
+            map.insert("table", name.to_string());
+            map.insert("pk_type", pk_type.to_string());
+            map
+        };
+
+        let ddl = ddl.render(&data).map_err(|source| SchemaError::Template {
+            table: name.to_string(),
+            path: table_schema.to_path_buf(),
+            source,
+        })?;
+        self.connection().execute_batch(&ddl).for_table(name)?;
+        Ok(())
+    }
+
+    /// Load data into the named table from the CSV file at the given table_data path
+    fn load_data(&self, name: &str, table_data: &Path) -> Result<LoadStats, SchemaError> {
+        self.load_data_with_options(name, table_data, &LoadOptions::default())
+    }
+
+    /// The names of all the columns in the table, from PRAGMA table_info
+    fn table_columns(&self, name: &str) -> Result<Vec<String>, SchemaError> {
+        let columns = self
+            .connection()
+            .prepare("SELECT name FROM pragma_table_info(?1) ORDER BY cid")
+            .and_then(|mut stmt| {
+                stmt.query_map([name], |row| row.get(0))?
+                    .collect::<Result<Vec<String>, _>>()
+            })
+            .for_table(name)?;
+
+        if columns.is_empty() {
+            return Err(SchemaError::NoSuchTable(name.to_string()));
+        }
+        Ok(columns)
+    }
+
+    /// Load data into the named table from the CSV file at the given table_data path
+    fn load_data_with_options(
+        &self,
+        name: &str,
+        table_data: &Path,
+        options: &LoadOptions<'_>,
+    ) -> Result<LoadStats, SchemaError> {
+        self.import_data(name, table_data, options)
+            .map(|imported| imported.stats)
+    }
+
+    /// Load data into the named table from the CSV file at the given table_data path, with the size and SHA-256
+    ///   of the file as it was read for the build_manifest
+    fn import_data(
+        &self,
+        name: &str,
+        table_data: &Path,
+        options: &LoadOptions<'_>,
+    ) -> Result<ImportedFile, SchemaError> {
+        let columns = self.table_columns(name)?;
+        let (headers, reader) = CsvHeaders::open(
+            name,
+            table_data,
+            &columns,
+            options.header_aliases,
+            options.filter,
+        )?;
+
+        let stats = insert_records(
+            self.connection(),
+            name,
+            table_data,
+            &headers,
+            options,
+            headers.parse(reader),
+        )?;
+        let (file_size, sha256) = headers.digest(table_data)?;
+
+        Ok(ImportedFile {
+            table_name: name.to_string(),
+            path: table_data.to_path_buf(),
+            stats,
+            file_size,
+            sha256,
+        })
+    }
+
+    /// Initialize all the lookup tables in lookup_tables, in the order of their names unless one references another
+    fn init_lookup_tables(
+        &self,
+        lookup_tables: &HashMap<String, LookupTable>,
+        table_schema: &Path,
+    ) -> Result<Vec<ImportedFile>, SchemaError> {
+        let mut imported = Vec::new();
+        for name in lookup_table_order(lookup_tables, table_schema)? {
+            let table = &lookup_tables[name];
+            info!("LOADING {name}");
+            let schema = table.schema.as_deref().unwrap_or(table_schema);
+            self.create_table(name, &table.pk_type, schema)?;
+            imported.push(self.import_data(name, &table.data, &LoadOptions::default())?);
+        }
+
+        Ok(imported)
+    }
+
+    /// Create and load all the tables defined in the Schema, after checking it with Schema::validate
+    fn load_from_schema(
+        &self,
+        schemas: &Schema,
+        old_switrs_path: &Path,
+        ccrs_data_path: &Path,
+    ) -> Result<(), SchemaError> {
+        schemas.validate(Some(old_switrs_path), ccrs_data_path)?;
+        self.defer_foreign_keys()?;
+
+        // initialize lookup tables
+        let mut imported = self
+            .connection()
+            .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)?;
+
+        // Build all the standard tables
+        for table_name in schemas.table_order()? {
+            let table = &schemas.tables[table_name];
+
+            match &table.data {
+                DataPath::CcrsData(regex) => imported.extend(self.load_ccrs_csvs(
+                    table,
+                    table_name,
+                    ccrs_data_path,
+                    regex,
+                    schemas,
+                )?),
+                DataPath::RawData(path) => imported.extend(self.load_from_csv(
+                    table,
+                    table_name,
+                    Some(old_switrs_path.join(path)),
+                    schemas,
+                )?),
+                DataPath::Path(path) => imported.extend(self.load_from_csv(
+                    table,
+                    table_name,
+                    Some(path.clone()),
+                    schemas,
+                )?),
+                DataPath::Empty => {
+                    self.load_from_csv(table, table_name, None, schemas)?;
+                }
+            };
+        }
+
+        // drop the crashes CCRS has marked as deleted
+        self.delete_ccrs_deleted_crashes()?;
+
+        // build fixup tables
+        self.fixup_tables(schemas)?;
+
+        // report the codes missing from the lookup tables
+        self.check_foreign_keys(schemas.strict_foreign_keys)?;
+
+        self.write_build_manifest(schemas, "build", ccrs_data_path, &imported)?;
+        Ok(())
+    }
+
+    /// Load new CCRS data into a DB previously created with load_from_schema,
+    ///   the legacy SWITRS data and other static tables are left as is
+    fn update_from_schema(
+        &self,
+        schemas: &Schema,
+        ccrs_data_path: &Path,
+    ) -> Result<(), SchemaError> {
+        // the legacy SWITRS data isn't loaded by update
+        schemas.validate(None, ccrs_data_path)?;
+        self.defer_foreign_keys()?;
+
+        let mut imported = Vec::new();
+        for table_name in schemas.table_order()? {
+            let table = &schemas.tables[table_name];
+
+            match &table.data {
+                DataPath::CcrsData(regex) => imported.extend(self.load_ccrs_csvs(
+                    table,
+                    table_name,
+                    ccrs_data_path,
+                    regex,
+                    schemas,
+                )?),
+                DataPath::RawData(_) | DataPath::Path(_) => {
+                    info!("SKIPPING {table_name}, only CCRS data is updated")
+                }
+                DataPath::Empty => {
+                    self.load_from_csv(table, table_name, None, schemas)?;
+                }
+            };
+        }
+
+        // drop the crashes CCRS has marked as deleted
+        self.delete_ccrs_deleted_crashes()?;
+
+        // rebuild fixup tables
+        self.fixup_tables(schemas)?;
+
+        // report the codes missing from the lookup tables
+        self.check_foreign_keys(schemas.strict_foreign_keys)?;
+
+        self.write_build_manifest(schemas, "update", ccrs_data_path, &imported)?;
+        Ok(())
+    }
+
+    /// Record the tool version, Schemas Toml hash and all the files loaded in the build_manifest tables,
+    ///   and any records that were rejected in load_rejections
+    fn write_build_manifest(
+        &self,
+        schemas: &Schema,
+        command: &str,
+        ccrs_data_path: &Path,
+        imported: &[ImportedFile],
+    ) -> Result<(), SchemaError> {
+        self.connection().execute(
+            "INSERT INTO build_manifest (command, tool_version, schema_sha256, ccrs_data_path, built_at)
+            VALUES(?1, ?2, ?3, ?4, strftime('%Y-%m-%dT%H:%M:%S', 'now'))",
+            (
+                command,
+                env!("CARGO_PKG_VERSION"),
+                &schemas.sha256,
+                ccrs_data_path.to_string_lossy(),
+            ),
+        ).for_table("build_manifest")?;
+        let build_id = self.connection().last_insert_rowid();
+
+        let mut insert_file = self.connection().prepare(
+            "INSERT INTO build_manifest_files (build_id, table_name, path, file_size, sha256, row_count, skipped_count, errored_count, filtered_count)
+            VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        ).for_table("build_manifest_files")?;
+        let mut insert_rejection = self.connection().prepare(
+            "INSERT INTO load_rejections (build_id, table_name, path, line_number, record, error_kind, error_message)
+            VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        ).for_table("load_rejections")?;
+        for ImportedFile {
+            table_name,
+            path,
+            stats,
+            file_size,
+            sha256,
+        } in imported
+        {
+            insert_file
+                .execute((
+                    build_id,
+                    table_name,
+                    path.to_string_lossy(),
+                    file_size,
+                    sha256,
+                    stats.rows,
+                    stats.skipped,
+                    stats.errored,
+                    stats.filtered,
+                ))
+                .for_table("build_manifest_files")?;
+
+            for rejection in &stats.rejections {
+                insert_rejection
+                    .execute((
+                        build_id,
+                        table_name,
+                        path.to_string_lossy(),
+                        rejection.line,
+                        &rejection.record,
+                        rejection.kind.as_str(),
+                        &rejection.message,
+                    ))
+                    .for_table("load_rejections")?;
+            }
+        }
+
+        info!(
+            "RECORDED {count} files in the build_manifest for {command} {build_id}",
+            count = imported.len()
+        );
+        Ok(())
+    }
+
+    /// Check a built DB before it is used, with the sqlite integrity_check, and that every table with rows loaded
+    ///   by the last build, or the updates since, still has rows
+    fn check_build(&self) -> Result<(), SchemaError> {
+        let problems = self
+            .connection()
+            .prepare("PRAGMA integrity_check")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .for_table("integrity_check")?;
+        if problems != ["ok"] {
+            return Err(SchemaError::BadBuild(format!(
+                "integrity_check failed: {}",
+                problems.join("; ")
+            )));
+        }
+
+        let tables = self
+            .connection()
+            .prepare(
+                "SELECT table_name FROM build_manifest_files
+                WHERE build_id >= (SELECT coalesce(max(id), 0) FROM build_manifest WHERE command = 'build')
+                GROUP BY table_name
+                HAVING sum(row_count) > 0",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .for_table("build_manifest_files")?;
+        if tables.is_empty() {
+            return Err(SchemaError::BadBuild(String::from(
+                "no data recorded in the build_manifest",
+            )));
+        }
+
+        for table in tables {
+            let count: usize = self
+                .connection()
+                .query_row(&format!("SELECT count(*) FROM \"{table}\""), [], |row| {
+                    row.get(0)
+                })
+                .for_table(&table)?;
+            if count == 0 {
+                return Err(SchemaError::BadBuild(format!(
+                    "{table} is empty, but rows were loaded into it"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stop enforcing the FOREIGN KEYs while loading, they are checked after the load with check_foreign_keys
+    ///
+    /// The bundled sqlite enforces them by default, which rejects every row with a code missing from a lookup table.
+    fn defer_foreign_keys(&self) -> Result<(), SchemaError> {
+        self.connection()
+            .pragma_update(None, "foreign_keys", false)
+            .for_table("foreign_keys")
+    }
+
+    /// Find the rows with codes that aren't in the tables their FOREIGN KEYs reference with PRAGMA foreign_key_check,
+    ///   and write them to the foreign_key_violations table, grouped by the table, column and code
+    ///
+    /// The foreign keys aren't enforced while loading, see defer_foreign_keys, so the rows with unknown codes are kept,
+    ///   and only show up as NULL names in the views. With strict, any violation fails the build once they are all written.
+    fn check_foreign_keys(&self, strict: bool) -> Result<Vec<ForeignKeyViolation>, SchemaError> {
+        let violated = self
+            .connection()
+            .prepare(
+                "SELECT \"table\", fkid, parent FROM pragma_foreign_key_check
+                GROUP BY \"table\", fkid, parent
+                ORDER BY \"table\", fkid",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get(2)?))
+                })?
+                .collect::<Result<Vec<(_, _, String)>, _>>()
+            })
+            .for_table("pragma_foreign_key_check")?;
+
+        let mut violations = Vec::new();
+        for (table, fkid, parent) in violated {
+            let columns = self
+                .connection()
+                .prepare(
+                    "SELECT \"from\" FROM pragma_foreign_key_list(?1) WHERE id = ?2 ORDER BY seq",
+                )
+                .and_then(|mut stmt| {
+                    stmt.query_map((&table, fkid), |row| row.get(0))?
+                        .collect::<Result<Vec<String>, _>>()
+                })
+                .for_table(&table)?;
+            let code = columns
+                .iter()
+                .map(|column| format!("t.\"{column}\""))
+                .collect::<Vec<_>>()
+                .join(" || ',' || ");
+
+            let codes = self
+                .connection()
+                .prepare(&format!(
+                    "SELECT CAST({code} AS TEXT) AS code, count(*) FROM pragma_foreign_key_check(?1) AS fk
+                    JOIN \"{table}\" AS t ON t.rowid = fk.rowid
+                    WHERE fk.fkid = ?2
+                    GROUP BY code
+                    ORDER BY code"
+                ))
+                .and_then(|mut stmt| {
+                    stmt.query_map((&table, fkid), |row| Ok((row.get(0)?, row.get(1)?)))?
+                        .collect::<Result<Vec<(Option<String>, usize)>, _>>()
+                })
+                .for_table(&table)?;
+
+            violations.extend(codes.into_iter().map(|(code, rows)| ForeignKeyViolation {
+                table: table.clone(),
+                column: columns.join(","),
+                parent: parent.clone(),
+                code: code.unwrap_or_default(),
+                rows,
+            }));
+        }
+
+        // the violations are always rebuilt from scratch
+        self.connection()
+            .execute(&format!("DELETE FROM {FOREIGN_KEY_VIOLATIONS_TABLE}"), [])
+            .for_table(FOREIGN_KEY_VIOLATIONS_TABLE)?;
+        let mut insert = self
+            .connection()
+            .prepare(&format!(
+                "INSERT INTO {FOREIGN_KEY_VIOLATIONS_TABLE} (table_name, column_name, parent_table, code, row_count)
+                VALUES(?1, ?2, ?3, ?4, ?5)"
+            ))
+            .for_table(FOREIGN_KEY_VIOLATIONS_TABLE)?;
+        for violation in &violations {
+            warn!(
+                "UNKNOWN {parent} '{code}' in {table}.{column}, {rows} rows",
+                parent = violation.parent,
+                code = violation.code,
+                table = violation.table,
+                column = violation.column,
+                rows = violation.rows
+            );
+            insert
+                .execute((
+                    &violation.table,
+                    &violation.column,
+                    &violation.parent,
+                    &violation.code,
+                    violation.rows,
+                ))
+                .for_table(FOREIGN_KEY_VIOLATIONS_TABLE)?;
+        }
+
+        let rows = violations.iter().map(|v| v.rows).sum();
+        info!(
+            "CHECKED foreign keys, {rows} rows have {codes} unknown codes",
+            codes = violations.len()
+        );
+        if strict && !violations.is_empty() {
+            return Err(SchemaError::ForeignKeyViolations {
+                codes: violations.len(),
+                rows,
+            });
+        }
+
+        Ok(violations)
+    }
+
+    /// Load CCRS data tables, only the files that changed since they were last imported are loaded
+    ///
+    /// Rows are upserted, so newer report versions replace older ones, and each file imported is
+    ///   recorded in the ccrs_imports table. The files are parsed in parallel, but loaded in the order of their names.
+    ///   Each row records the release of its file, rows with a parent are only kept from the release of their parent,
+    ///   and all the files of the child tables are loaded again after any file of the parent is.
+    fn load_ccrs_csvs(
+        &self,
+        table: &PrimaryTable,
+        table_name: &str,
+        ccrs_data_path: &Path,
+        regex_name: &str,
+        schemas: &Schema,
+    ) -> Result<Vec<ImportedFile>, SchemaError> {
+        info!("LOADING CCRS Table: {table_name}");
+        let regex = Regex::new(regex_name).map_err(|source| SchemaError::Regex {
+            table: table_name.to_string(),
+            source,
+        })?;
+
+        //
+        // load ccrs data
+        if !ccrs_data_path.is_dir() {
+            return Err(SchemaError::MissingDataDir {
+                path: ccrs_data_path.to_path_buf(),
+            });
+        }
+
+        let mut paths = Vec::<PathBuf>::new();
+        let dir = fs::read_dir(ccrs_data_path).for_path(ccrs_data_path)?;
+        for entry in dir {
+            let entry = entry.for_path(ccrs_data_path)?;
+            if !entry.file_type().for_path(&entry.path())?.is_file() {
+                continue;
+            }
+
+            if regex.is_match(&entry.file_name().to_string_lossy()) {
+                paths.push(entry.path());
+            }
+        }
+        paths.sort();
+
+        // always create the table, even if there is no data for it
+        if paths.is_empty() {
+            warn!("no files matching {regex_name} found for {table_name}");
+        }
+        self.load_from_csv(table, table_name, None, schemas)?;
+        let filter = self.row_filter(table, &schemas.jurisdiction)?;
+
+        // skip the files that haven't changed since they were last imported
+        let mut changed = Vec::new();
+        for path in paths {
+            let file_name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            let metadata = fs::metadata(&path).for_path(&path)?;
+            let file_size = metadata.len() as i64;
+            let file_modified = metadata
+                .modified()
+                .for_path(&path)?
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64;
+
+            let unchanged: bool = self.connection().query_row(
+                &format!(
+                    "SELECT count(*) > 0 FROM {CCRS_IMPORTS_TABLE}
+                    WHERE table_name = ?1 AND file_name = ?2 AND file_size = ?3 AND file_modified = ?4"
+                ),
+                (table_name, &file_name, file_size, file_modified),
+                |row| row.get(0),
+            ).for_table(CCRS_IMPORTS_TABLE)?;
+            if unchanged {
+                info!("SKIPPING {file_name}, unchanged since last import");
+                continue;
+            }
+
+            changed.push((path, file_name, file_size, file_modified));
+        }
+
+        // the release column isn't in the CSV files, it's filled from the file name
+        let mut columns = self.table_columns(table_name)?;
+        let has_release = columns.iter().any(|c| c == CCRS_RELEASE_COLUMN);
+        columns.retain(|c| c != CCRS_RELEASE_COLUMN);
+
+        // rows are only limited to the release of their parent if it has one too
+        let mut options =
+            table.load_options(OnConflict::Upsert, schemas.batch_size(), filter.as_ref());
+        if let Some(parent) = options.parent {
+            let parent_release = self
+                .table_columns(&parent.table)?
+                .iter()
+                .any(|c| c == CCRS_RELEASE_COLUMN);
+            if !(has_release && parent_release) {
+                options.parent = None;
+            }
+        }
+        let (headers, readers): (Vec<_>, Vec<_>) = changed
+            .iter()
+            .map(|(path, ..)| {
+                CsvHeaders::open(
+                    table_name,
+                    path,
+                    &columns,
+                    options.header_aliases,
+                    options.filter,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .unzip();
+
+        // the files are parsed on the parse workers, one per CPU by default, and the records are inserted by this
+        //   thread in the order of the files
+        let mut imported = Vec::new();
+        let mut errored = 0;
+        parse_in_order(
+            headers.iter().zip(readers).collect(),
+            schemas.parse_workers(),
+            options.batch_size,
+            |(headers, reader)| headers.parse(reader),
+            |index, records| {
+                let (path, file_name, file_size, file_modified) = &changed[index];
+                let headers = &headers[index];
+                info!("LOADING {table_name} from {path}", path = path.display());
+                let options = LoadOptions {
+                    release: has_release.then(|| ccrs_release(&regex, file_name)),
+                    ..options
+                };
+                let stats = insert_records(
+                    self.connection(),
+                    table_name,
+                    path,
+                    headers,
+                    &options,
+                    records,
+                )?;
+                errored += stats.errored;
+                table.check_error_budget(table_name, errored)?;
+                let (digest_size, sha256) = headers.digest(path)?;
+
+                self.connection().execute(
+                    &format!(
+                        "INSERT OR REPLACE INTO {CCRS_IMPORTS_TABLE} (table_name, file_name, file_size, file_modified, row_count, imported_at)
+                        VALUES(?1, ?2, ?3, ?4, ?5, strftime('%Y-%m-%dT%H:%M:%S', 'now'))"
+                    ),
+                    (
+                        table_name,
+                        file_name,
+                        file_size,
+                        file_modified,
+                        stats.records(),
+                    ),
+                ).for_table(CCRS_IMPORTS_TABLE)?;
+
+                imported.push(ImportedFile {
+                    table_name: table_name.to_string(),
+                    path: path.clone(),
+                    stats,
+                    file_size: digest_size,
+                    sha256,
+                });
+                Ok::<_, SchemaError>(())
+            },
+        )?;
+
+        // drop the rows of older versions of their parent, e.g. the parties of a crash replaced by a newer report
+        if let Some(ParentTable { table, key }) = options.parent {
+            let count = self
+                .connection()
+                .execute(
+                    &format!(
+                        "DELETE FROM {table_name} WHERE NOT EXISTS (
+                            SELECT 1 FROM {table}
+                            WHERE {table}.{key} = {table_name}.{key} AND {table}.{CCRS_RELEASE_COLUMN} = {table_name}.{CCRS_RELEASE_COLUMN}
+                        )"
+                    ),
+                    [],
+                )
+                .for_table(table_name)?;
+            info!("DELETED {count} {table_name} of older versions of their {table}");
+        }
+
+        // a new version of a row may have new child rows in the files of its release, so they're all loaded again
+        if !imported.is_empty() && has_release {
+            for (child, _) in schemas.tables.iter().filter(|(_, child)| {
+                child
+                    .parent
+                    .as_ref()
+                    .is_some_and(|parent| parent.table == table_name)
+            }) {
+                self.connection()
+                    .execute(
+                        &format!("DELETE FROM {CCRS_IMPORTS_TABLE} WHERE table_name = ?1"),
+                        [child],
+                    )
+                    .for_table(CCRS_IMPORTS_TABLE)?;
+            }
+        }
+
+        Ok(imported)
+    }
+
+    /// insert_from_csvs
+    fn load_from_csv(
+        &self,
+        table: &PrimaryTable,
+        table_name: &str,
+        data_path: Option<PathBuf>,
+        schemas: &Schema,
+    ) -> Result<Option<ImportedFile>, SchemaError> {
+        info!("LOADING {table_name} from {data_path:?}");
+        self.connection().create_table_for_jurisdiction(
+            table_name,
+            "",
+            &table.schema,
+            &schemas.jurisdiction,
+        )?;
+
+        let Some(path) = data_path else {
+            return Ok(None);
+        };
+        let filter = self.row_filter(table, &schemas.jurisdiction)?;
+        let imported = self.connection().import_data(
+            table_name,
+            &path,
+            &table.load_options(OnConflict::Ignore, schemas.batch_size(), filter.as_ref()),
+        )?;
+        table.check_error_budget(table_name, imported.stats.errored)?;
+
+        Ok(Some(imported))
+    }
+
+    /// The filter for the rows of the table that are in the jurisdiction, None if the table has no jurisdiction-column
+    ///   or parent, or the jurisdiction is all of California
+    fn row_filter(
+        &self,
+        table: &PrimaryTable,
+        jurisdiction: &Jurisdiction,
+    ) -> Result<Option<RowFilter>, SchemaError> {
+        if jurisdiction.is_empty() {
+            return Ok(None);
+        }
+
+        let (column, source, query) = match (&table.jurisdiction_column, &table.parent) {
+            (Some(column), _) => (
+                column.as_str(),
+                "cnty_city_loc",
+                jurisdiction.cnty_city_loc_query("city"),
+            ),
+            (None, Some(ParentTable { table, key })) => (
+                key.as_str(),
+                table.as_str(),
+                format!("SELECT CAST({key} AS TEXT) FROM {table}"),
+            ),
+            (None, None) => return Ok(None),
+        };
+
+        let values = self
+            .connection()
+            .prepare(&query)
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| row.get::<_, Option<String>>(0))?
+                    .filter_map(Result::transpose)
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .for_table(source)?;
+
+        Ok(Some(RowFilter::new(column, values)))
+    }
+
+    /// Delete the CCRS crashes marked with is_deleted, along with their parties and injured/witnesses/passengers,
+    ///   returns the number of crashes deleted
+    ///
+    /// CCRS marks a crash as deleted with a newer report version, so this must be run after all the CCRS data is loaded.
+    ///   The deleted crashes are kept in ccrs_deleted_crashes, so they are deleted again when a later update loads an
+    ///   older version of them, like a file of an older release that changed.
+    fn delete_ccrs_deleted_crashes(&self) -> Result<usize, SchemaError> {
+        self.connection()
+            .execute(
+                &format!(
+                    "INSERT INTO {CCRS_DELETED_CRASHES_TABLE} (collision_id, report_version, deleted_at)
+                    SELECT collision_id, report_version, strftime('%Y-%m-%dT%H:%M:%S', 'now') FROM ccrs_crashes
+                    WHERE upper(is_deleted) IN ('TRUE', 'T', 'Y', '1')
+                    ON CONFLICT DO UPDATE SET report_version = excluded.report_version, deleted_at = excluded.deleted_at
+                    WHERE coalesce(excluded.report_version, 0) > coalesce({CCRS_DELETED_CRASHES_TABLE}.report_version, 0)"
+                ),
+                [],
+            )
+            .for_table(CCRS_DELETED_CRASHES_TABLE)?;
+
+        // a newer version than the one deleted is kept
+        let deleted_crashes = format!(
+            "SELECT c.collision_id FROM ccrs_crashes c JOIN {CCRS_DELETED_CRASHES_TABLE} d ON d.collision_id = c.collision_id
+            WHERE coalesce(c.report_version, 0) <= coalesce(d.report_version, 0)"
+        );
+
+        for table in ["ccrs_parties", "ccrs_injured_witness_passengers"] {
+            let count = self
+                .connection()
+                .execute(
+                    &format!("DELETE FROM {table} WHERE collision_id IN ({deleted_crashes})"),
+                    [],
+                )
+                .for_table(table)?;
+            info!("DELETED {count} {table} of deleted crashes");
+        }
+
+        let count = self
+            .connection()
+            .execute(
+                &format!("DELETE FROM ccrs_crashes WHERE collision_id IN ({deleted_crashes})"),
+                [],
+            )
+            .for_table("ccrs_crashes")?;
+        info!("DELETED {count} ccrs_crashes marked as deleted");
+
+        Ok(count)
+    }
+
+    /// Run tasks to fill fixup tables, or produce csv's which add lookup tables to cleanup data
+    fn fixup_tables(&self, schemas: &Schema) -> Result<(), SchemaError> {
+        let mut unknown_roads = self.fixup_roads(&RoadTables::SWITRS, schemas)?;
+        unknown_roads.extend(self.fixup_roads(&RoadTables::CCRS, schemas)?);
+        self.suggest_roads(&unknown_roads, schemas)?;
+
+        Ok(())
+    }
+
+    /// Rank the known roads, the correct_rd of the berkeley_road_typos, for each road that couldn't be corrected,
+    ///   the suggestions are written to the road-suggestions file, if one is set, see RoadMatcher
+    ///
+    /// The first two columns of the suggestions are the columns of berkeley-tables/BERKELEY_ROAD_TYPOS.csv, so the
+    ///   rows that are right can be added to it as they are.
+    fn suggest_roads(
+        &self,
+        unknown_roads: &[UnknownRoad],
+        schemas: &Schema,
+    ) -> Result<(), SchemaError> {
+        const SUGGESTIONS_PER_ROAD: usize = 3;
+
+        let matcher = RoadMatcher::new(known_roads(self.connection())?);
+
+        // each road is suggested once, the roads in the most crashes first
+        let mut roads: BTreeMap<&str, (usize, &str)> = BTreeMap::new();
+        for road in unknown_roads {
+            roads
+                .entry(&road.normalized)
+                .or_insert((0, &road.original))
+                .0 += 1;
+        }
+        let mut roads = roads.into_iter().collect::<Vec<_>>();
+        roads.sort_by(|(_, (a, _)), (_, (b, _))| b.cmp(a));
+
+        let mut suggestions = Vec::new();
+        for (normalized, (crashes, original)) in &roads {
+            for (rank, suggestion) in matcher
+                .suggest(normalized, SUGGESTIONS_PER_ROAD)
+                .into_iter()
+                .enumerate()
+            {
+                if rank == 0 {
+                    info!("UNKNOWN road '{normalized}' in {crashes} crashes, e.g. '{original}', suggested '{road}' ({confidence:.2})",
+                        road = suggestion.road,
+                        confidence = suggestion.confidence
+                    );
+                }
+                suggestions.push([
+                    normalized.to_string(),
+                    suggestion.road.to_string(),
+                    format!("{:.2}", suggestion.confidence),
+                    (rank + 1).to_string(),
+                    crashes.to_string(),
+                    original.to_string(),
+                ]);
+            }
+        }
+
+        let path = match (&schemas.road_suggestions, schemas.corrections) {
+            (Some(path), Corrections::Write) => path,
+            _ => {
+                if !roads.is_empty() {
+                    warn!("{count} unknown roads in {crashes} crashes, add them to berkeley-tables/BERKELEY_ROAD_TYPOS.csv, --road-suggestions writes suggestions for them",
+                        count = roads.len(),
+                        crashes = unknown_roads.len()
+                    );
+                }
+                return Ok(());
+            }
+        };
+
+        info!("WRITING road suggestions to {path}", path = path.display());
+        let mut csv = csv::Writer::from_path(path).for_path(path)?;
+        csv.write_record([
+            "normalized_rd",
+            "correct_rd",
+            "confidence",
+            "rank",
+            "crashes",
+            "original_rd",
+        ])
+        .for_path(path)?;
+        for suggestion in &suggestions {
+            csv.write_record(suggestion).for_path(path)?;
+        }
+        csv.flush().for_path(path)?;
+
+        if !roads.is_empty() {
+            warn!("{count} unknown roads in {crashes} crashes, review the suggestions in {path} and add the right ones to berkeley-tables/BERKELEY_ROAD_TYPOS.csv",
+                count = roads.len(),
+                crashes = unknown_roads.len(),
+                path = path.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Fill the normalized roads table for the crash data source with the output of normalize_road
+    fn normalize_roads(
+        &self,
+        roads: &RoadTables,
+        abbreviations: &RoadAbbreviations,
+    ) -> Result<usize, SchemaError> {
+        let RoadTables {
+            source,
+            source_id,
+            source_primary_rd,
+            source_secondary_rd,
+            normalized,
+            id,
+            ..
+        } = roads;
+
+        // the table is rebuilt from scratch, e.g. when updating an existing DB
+        self.connection()
+            .execute(&format!("DELETE FROM {normalized}"), [])
+            .for_table(normalized)?;
+
+        // when processing collision data, we will cleanup some data,
+        //   for that we have some custom insert and one off tables
+        let mut insert_road_stmt = self
+            .connection()
+            .prepare(&format!(
+                "INSERT INTO {normalized} (
+                {id},
+                primary_rd,
+                primary_rd_address,
+                primary_rd_block,
+                primary_rd_direction,
+                secondary_rd,
+                secondary_rd_address,
+                secondary_rd_block,
+                seconardy_rd_direction
+            ) VALUES(
+                ?,
+                ?,
+                ?,
+                ?,
+                ?,
+                ?,
+                ?,
+                ?,
+                ?
+            )"
+            ))
+            .for_table(normalized)?;
+
+        let mut select_roads = self
+            .connection()
+            .prepare(&format!(
+                "SELECT
+                CAST({source_id} AS TEXT) as case_id,
+                {source_primary_rd} as primary_rd,
+                {source_secondary_rd} as secondary_rd
+                FROM {source}"
+            ))
+            .for_table(source)?;
+
+        let mut count = 0;
+        let mut roads = select_roads.query([]).for_table(source)?;
+        while let Some(road) = roads.next().for_table(source)? {
+            // add normalized roads from the collisions table
+            let case_id = text_column(road, source, "case_id")?.unwrap_or_default();
+            let primary_rd = text_column(road, source, "primary_rd")?;
+            let secondary_rd = text_column(road, source, "secondary_rd")?;
+
+            let primary_rd = primary_rd.map(|r| abbreviations.normalize_road(r));
+            let secondary_rd = secondary_rd.map(|r| abbreviations.normalize_road(r));
+
+            insert_road_stmt.insert([
+                Some(case_id),
+                primary_rd.as_ref().map(|r| r.road()).as_deref(),
+                primary_rd.as_ref().and_then(|r| r.number),
+                primary_rd.as_ref().and_then(|r| r.block),
+                primary_rd.as_ref().and_then(NormalizedRoad::direction),
+                secondary_rd.as_ref().map(|r| r.road()).as_deref(),
+                secondary_rd.as_ref().and_then(|r| r.number),
+                secondary_rd.as_ref().and_then(|r| r.block),
+                secondary_rd.as_ref().and_then(NormalizedRoad::direction),
+            ])
+            .inspect_err(|e| {
+                error!("error on insert into {normalized} {id}={case_id},primary={primary_rd:?},secondary={secondary_rd:?}: {e}");
+            })
+            .for_table(normalized)?;
+
+            count += 1;
+        }
+
+        info!("NORMALIZED {count} roads into {normalized}");
+        Ok(count)
+    }
+
+    /// This uses the Berkeley Road Typos and the Corrected Roads to construct a lookup table with correct road names
+    ///   for each Case ID, the corrections are written to the corrected-roads file or checked, see Corrections,
+    ///   returns the roads that couldn't be corrected
+    fn fixup_roads(
+        &self,
+        roads: &RoadTables,
+        schemas: &Schema,
+    ) -> Result<Vec<UnknownRoad>, SchemaError> {
+        self.normalize_roads(roads, &schemas.road_abbreviations()?)?;
+
+        let RoadTables {
+            source,
+            source_id,
+            source_primary_rd,
+            source_secondary_rd,
+            normalized,
+            id,
+            corrected,
+        } = roads;
+
+        //
         // find all roads not in our known roads list
-        //   we will prefer names that match the "known list", the move to the normalized name matching, then typos
+        //   we will prefer names that match the "known list", the move to the normalized name matching, then typos,
+        //   and last the normalized name itself when it is in the "known list"
         let mut select_roads = self
             .connection()
-            .prepare("
+            .prepare(&format!("
                 SELECT
-                n.case_id as case_id,
+                n.{id} as case_id,
                 n.primary_rd as normal_primary_rd,
                 n.primary_rd_address,
                 n.primary_rd_block,
@@ -446,90 +2935,172 @@ pub trait NewDB {
                 n.secondary_rd_address,
                 n.secondary_rd_block,
                 n.seconardy_rd_direction,
-                c.primary_rd as original_primary_rd,
-                c.secondary_rd as original_secondary_rd,
+                c.{source_primary_rd} as original_primary_rd,
+                c.{source_secondary_rd} as original_secondary_rd,
                 cr.primary_rd as correct_primary_rd,
                 cr.secondary_rd as correct_secondary_rd,
-                cp.primary_rd as verified_primary_rd,
-                cs.secondary_rd as verified_secondary_rd,
+                cp.{source_primary_rd} as verified_primary_rd,
+                cs.{source_secondary_rd} as verified_secondary_rd,
+                kp.correct_rd as known_primary_rd,
+                ks.correct_rd as known_secondary_rd,
                 tp.correct_rd as suggest_primary_rd,
                 ts.correct_rd as suggest_secondary_rd
                 FROM
-                switrs_normalized_roads as n
-                LEFT JOIN switrs_collisions as c ON c.case_id = n.case_id
-                LEFT JOIN switrs_corrected_roads as cr ON cr.case_id = n.case_id
-                LEFT JOIN switrs_collisions as cp ON cp.case_id = n.case_id AND cp.primary_rd in (SELECT DISTINCT correct_rd FROM berkeley_road_typos)
-                LEFT JOIN switrs_collisions as cs ON cs.case_id = n.case_id AND cs.secondary_rd in (SELECT DISTINCT correct_rd FROM berkeley_road_typos)
+                {normalized} as n
+                LEFT JOIN {source} as c ON c.{source_id} = n.{id}
+                LEFT JOIN {corrected} as cr ON cr.{id} = n.{id}
+                LEFT JOIN {source} as cp ON cp.{source_id} = n.{id} AND cp.{source_primary_rd} in (SELECT DISTINCT correct_rd FROM berkeley_road_typos)
+                LEFT JOIN {source} as cs ON cs.{source_id} = n.{id} AND cs.{source_secondary_rd} in (SELECT DISTINCT correct_rd FROM berkeley_road_typos)
+                LEFT JOIN (SELECT DISTINCT correct_rd FROM berkeley_road_typos) as kp ON kp.correct_rd = n.primary_rd
+                LEFT JOIN (SELECT DISTINCT correct_rd FROM berkeley_road_typos) as ks ON ks.correct_rd = n.secondary_rd
                 LEFT JOIN berkeley_road_typos as tp ON tp.normalized_rd = n.primary_rd
                 LEFT JOIN berkeley_road_typos as ts ON ts.normalized_rd = n.secondary_rd
                 ORDER BY case_id
-            ")?;
-        let mut corrections = select_roads.query([])?;
-
-        // we will always rebuild the corrections file.
-        let mut corrected_roads = fs::OpenOptions::new()
-            .truncate(true)
-            .write(true)
-            .open("berkeley-tables/CORRECTED_ROADS.csv")?;
-        writeln!(corrected_roads, "case_id,primary_rd,secondary_rd")?;
-        while let Some(correction) = corrections.next()? {
-            let case_id = correction.get_ref("case_id")?.as_str()?;
-            let normal_primary_rd = correction.get_ref("normal_primary_rd")?.as_str()?;
-            let normal_secondary_rd = correction.get_ref("normal_secondary_rd")?.as_str()?;
-            let original_primary_rd = correction.get_ref("original_primary_rd")?.as_str()?;
-            let original_secondary_rd = correction.get_ref("original_secondary_rd")?.as_str()?;
-
-            let correct_primary_rd = correction.get_ref("correct_primary_rd")?.as_str_or_null()?;
-            let correct_secondary_rd = correction
-                .get_ref("correct_secondary_rd")?
-                .as_str_or_null()?;
-
-            let verified_primary_rd = correction
-                .get_ref("verified_primary_rd")?
-                .as_str_or_null()?;
-            let verified_secondary_rd = correction
-                .get_ref("verified_secondary_rd")?
-                .as_str_or_null()?;
-            let suggest_primary_rd = correction.get_ref("suggest_primary_rd")?.as_str_or_null()?;
-            let suggest_secondary_rd = correction
-                .get_ref("suggest_secondary_rd")?
-                .as_str_or_null()?;
+            ")).for_table(normalized)?;
+        let mut corrections = select_roads.query([]).for_table(normalized)?;
+
+        // the corrections are always rebuilt from scratch
+        let mut lines = vec![format!("{id},primary_rd,secondary_rd")];
+        let mut corrected_roads = Vec::new();
+        let mut unknown_roads = Vec::new();
+        while let Some(correction) = corrections.next().for_table(normalized)? {
+            let text = |column| text_column(correction, normalized, column);
+            let case_id = text("case_id")?.unwrap_or_default();
+            let normal_primary_rd = text("normal_primary_rd")?.unwrap_or("");
+            let normal_secondary_rd = text("normal_secondary_rd")?.unwrap_or("");
+            let original_primary_rd = text("original_primary_rd")?.unwrap_or("");
+            let original_secondary_rd = text("original_secondary_rd")?.unwrap_or("");
+
+            let correct_primary_rd = text("correct_primary_rd")?;
+            let correct_secondary_rd = text("correct_secondary_rd")?;
+
+            let verified_primary_rd = text("verified_primary_rd")?;
+            let verified_secondary_rd = text("verified_secondary_rd")?;
+            let known_primary_rd = text("known_primary_rd")?;
+            let known_secondary_rd = text("known_secondary_rd")?;
+            let suggest_primary_rd = text("suggest_primary_rd")?;
+            let suggest_secondary_rd = text("suggest_secondary_rd")?;
 
             let primary_rd = correct_primary_rd
-                .or(verified_primary_rd.or(suggest_primary_rd))
+                .or(verified_primary_rd)
+                .or(suggest_primary_rd)
+                .or(known_primary_rd)
                 .unwrap_or("");
             let secondary_rd = correct_secondary_rd
-                .or(verified_secondary_rd.or(suggest_secondary_rd))
+                .or(verified_secondary_rd)
+                .or(suggest_secondary_rd)
+                .or(known_secondary_rd)
                 .unwrap_or("");
 
-            writeln!(
-                corrected_roads,
-                "{case_id},\"{primary_rd}\",\"{secondary_rd}\""
-            )?;
+            lines.push(format!(
+                "{case_id},{roads}",
+                roads = quoted_csv(&[primary_rd, secondary_rd])
+            ));
+            corrected_roads.push((
+                case_id.to_string(),
+                primary_rd.to_string(),
+                secondary_rd.to_string(),
+            ));
+
+            // the unknown roads get suggestions in fixup_tables
+            for (column, road, normalized, original) in [
+                (
+                    "primary_rd",
+                    primary_rd,
+                    normal_primary_rd,
+                    original_primary_rd,
+                ),
+                (
+                    "secondary_rd",
+                    secondary_rd,
+                    normal_secondary_rd,
+                    original_secondary_rd,
+                ),
+            ] {
+                if road.is_empty() {
+                    debug!("UNKNOWN {case_id} {column}: '{original}', normalized '{normalized}'");
+                }
+                if road.is_empty() && !normalized.is_empty() {
+                    unknown_roads.push(UnknownRoad {
+                        case_id: case_id.to_string(),
+                        column,
+                        original: original.to_string(),
+                        normalized: normalized.to_string(),
+                    });
+                }
+            }
+        }
 
-            if primary_rd.is_empty() {
-                warn!("WARNING {case_id} has unknown primary_rd: {original_primary_rd}");
-                warn!("  to get of this warning add '{normal_primary_rd}' as 'normalized_rd' to berkeley-tables/BERKELEY_ROAD_TYPOS.csv and the 'correct_rd' entry");
-                warn!("  or add the original name '{original_primary_rd}' as 'normalized_rd' to berkeley-tables/BERKELEY_ROAD_TYPOS.csv and the 'correct_rd' entry");
+        match schemas.corrections {
+            Corrections::Write => {
+                let corrected_csv = schemas.corrected_roads_csv(corrected)?;
+                info!(
+                    "WRITING {corrected} to {corrected_csv}",
+                    corrected_csv = corrected_csv.display()
+                );
+                fs::File::create(&corrected_csv)
+                    .and_then(|file| {
+                        let mut file = io::BufWriter::new(file);
+                        for line in &lines {
+                            writeln!(file, "{line}")?;
+                        }
+                        file.flush()
+                    })
+                    .for_path(&corrected_csv)?;
             }
+            Corrections::Check => {
+                let checked_in = schemas.lookup_table_data(corrected)?;
+                let checked_in_lines =
+                    resources::read_to_string(&checked_in).for_path(&checked_in)?;
+                let (removed, added) = diff_lines(&checked_in_lines, &lines);
+                for line in &removed {
+                    info!("- {line}");
+                }
+                for line in &added {
+                    info!("+ {line}");
+                }
 
-            if secondary_rd.is_empty() {
-                warn!("WARNING {case_id} has unknown secondary_rd: {original_secondary_rd}");
-                warn!("  to get of this warning add '{normal_secondary_rd}' as 'normalized_rd' to berkeley-tables/BERKELEY_ROAD_TYPOS.csv and the 'correct_rd' entry");
-                warn!("  or add the original name '{original_secondary_rd}' as 'normalized_rd' to berkeley-tables/BERKELEY_ROAD_TYPOS.csv and the 'correct_rd' entry");
+                if !removed.is_empty() || !added.is_empty() {
+                    return Err(SchemaError::CorrectionsOutOfDate {
+                        table: corrected.to_string(),
+                        path: checked_in,
+                        removed: removed.len(),
+                        added: added.len(),
+                    });
+                }
+                info!(
+                    "CHECKED {corrected} in {checked_in}, no changes",
+                    checked_in = checked_in.display()
+                );
             }
+            Corrections::Skip => info!("SKIPPED writing {corrected}"),
         }
 
-        // reload data from the CORRECTED_ROADS
-        info!("RELOADING switrs_corrected_roads with any new roads");
-        self.load_data_with_options(
-            "switrs_corrected_roads",
-            Path::new("berkeley-tables/CORRECTED_ROADS.csv"),
-            true,
-            true,
-        )?;
+        // add the new corrections to the table, the existing ones are already loaded from the lookup table data
+        info!("RELOADING {corrected} with any new roads");
+        let mut insert = self
+            .connection()
+            .prepare(&format!(
+            "INSERT OR IGNORE INTO {corrected} ({id}, primary_rd, secondary_rd) VALUES(?1, ?2, ?3)"
+        ))
+            .for_table(corrected)?;
+        let mut count = 0;
+        for (case_id, primary_rd, secondary_rd) in &corrected_roads {
+            let non_empty = |road: &String| (!road.is_empty()).then(|| road.clone());
+            if insert
+                .execute((case_id, non_empty(primary_rd), non_empty(secondary_rd)))
+                .for_table(corrected)?
+                > 0
+            {
+                debug!(
+                    "INSERTED {id}={case_id},primary_rd={primary_rd},secondary_rd={secondary_rd}"
+                );
+                count += 1;
+            }
+        }
+        info!("INSERTED {count} new roads into {corrected}");
 
-        Ok(())
+        Ok(unknown_roads)
     }
 }
 
@@ -539,72 +3110,394 @@ impl NewDB for Connection {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
-struct NormalizedRoad<'a> {
-    road: Cow<'a, str>,
-    address: Option<&'a str>,
-    block: Option<&'a str>,
-    direction: Option<&'a str>,
+/// A road of a crash that couldn't be corrected, by the corrected roads, the known roads or the typos
+#[derive(Debug)]
+pub struct UnknownRoad {
+    /// The id of the crash
+    pub case_id: String,
+    /// primary_rd or secondary_rd
+    pub column: &'static str,
+    /// The road as it is in the crash data
+    pub original: String,
+    /// The road as it was looked up, see RoadAbbreviations::normalize_road
+    pub normalized: String,
 }
 
-/// Takes Road names and removes address information, or block information
-fn normalize_road(road: &str) -> NormalizedRoad<'_> {
-    static ADDRESS_MATCHER: OnceLock<Regex> = OnceLock::new();
-    static REPLACE_SPACES: OnceLock<Regex> = OnceLock::new();
+/// Tables and columns used to normalize and correct the road names of one source of crash data
+#[derive(Debug)]
+pub struct RoadTables {
+    /// table with the raw crash data, e.g. switrs_collisions
+    pub(crate) source: &'static str,
+    /// unique id of the crash in the source table
+    pub(crate) source_id: &'static str,
+    /// primary road column in the source table
+    pub(crate) source_primary_rd: &'static str,
+    /// secondary road column in the source table
+    pub(crate) source_secondary_rd: &'static str,
+    /// table to fill with the normalized road names
+    normalized: &'static str,
+    /// id column of the normalized and corrected tables
+    pub(crate) id: &'static str,
+    /// lookup table of the corrected road names for each crash, also the name in [corrected-roads]
+    pub(crate) corrected: &'static str,
+}
 
-    let address_matcher: &Regex = ADDRESS_MATCHER.get_or_init(|| {
-        Regex::new(
-            r"(^(?<address_pre>\d+) +)?((?<direction_pre>NORTHBOUND|EASTBOUND|WESTBOUND|SOUTHBOUND|NORTH|EAST|WEST|SOUTH|N/B|E/B|W/B|S/B|NB|EB|WB|SB|N|E|W|S) +)?(?<street>(I-\d+)|(RT +\d+)|(\w+[ \w]+[[:alpha:]]+))([\.,])*(/B)?( +(?<direction_post>NORTHBOUND|EASTBOUND|WESTBOUND|SOUTHBOUND|NORTH|EAST|WEST|SOUTH|N/B|E/B|W/B|S/B|NB|EB|WB|SB|N|E|W|S)[\.,/]*)?( +((?<address_post>\d+)|(\(?(?<block>\d+) BLOCK\)?))$)?",
-        )
-        .expect("bad regular expression")
-    });
+impl RoadTables {
+    /// Roads from the legacy SWITRS collisions
+    pub const SWITRS: Self = Self {
+        source: "switrs_collisions",
+        source_id: "case_id",
+        source_primary_rd: "primary_rd",
+        source_secondary_rd: "secondary_rd",
+        normalized: "switrs_normalized_roads",
+        id: "case_id",
+        corrected: "switrs_corrected_roads",
+    };
+
+    /// Roads from the CCRS crashes
+    pub const CCRS: Self = Self {
+        source: "ccrs_crashes",
+        source_id: "collision_id",
+        source_primary_rd: "primary_road",
+        source_secondary_rd: "secondary_road",
+        normalized: "ccrs_normalized_roads",
+        id: "collision_id",
+        corrected: "ccrs_corrected_roads",
+    };
+}
+
+/// The known roads, the correct_rd of the berkeley_road_typos
+pub(crate) fn known_roads(connection: &Connection) -> Result<Vec<String>, SchemaError> {
+    let mut known = connection
+        .prepare("SELECT DISTINCT correct_rd FROM berkeley_road_typos WHERE correct_rd IS NOT NULL")
+        .for_table("berkeley_road_typos")?;
+    known
+        .query_map([], |row| row.get::<_, String>(0))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .for_table("berkeley_road_typos")
+}
+
+/// The lines only in the checked in file, and the lines only in the proposed one, ignoring their order
+fn diff_lines<'a>(checked_in: &'a str, proposed: &'a [String]) -> (Vec<&'a str>, Vec<&'a str>) {
+    let checked_in_lines = checked_in.lines().collect::<HashSet<_>>();
+    let proposed_lines = proposed.iter().map(String::as_str).collect::<HashSet<_>>();
+
+    let removed = checked_in
+        .lines()
+        .filter(|line| !proposed_lines.contains(line))
+        .collect();
+    let added = proposed
+        .iter()
+        .map(String::as_str)
+        .filter(|line| !checked_in_lines.contains(line))
+        .collect();
+    (removed, added)
+}
+
+/// The text in the column of a row selected from the table, None for NULL
+fn text_column<'r>(
+    row: &'r Row<'_>,
+    table: &str,
+    column: &str,
+) -> Result<Option<&'r str>, SchemaError> {
+    row.get_ref(column)
+        .and_then(|value| Ok(value.as_str_or_null()?))
+        .for_table(table)
+}
+
+/// The release of a CCRS file, the first group of the table's path regex in the file name, e.g. 2024 for
+///   Crashes_2024.csv, or the whole name if the regex has no groups
+fn ccrs_release<'a>(regex: &Regex, file_name: &'a str) -> &'a str {
+    regex
+        .captures(file_name)
+        .and_then(|captures| captures.get(1).or_else(|| captures.get(0)))
+        .map_or(file_name, |release| release.as_str())
+}
+
+/// The fields as a line of CSV, each one quoted, with the quotes in them doubled, like the roads in the corrected roads
+///   and typos files
+pub(crate) fn quoted_csv(fields: &[&str]) -> String {
+    let mut csv = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::Always)
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+
+    // writing to a Vec can't fail
+    let line = csv
+        .write_record(fields)
+        .ok()
+        .and_then(|()| csv.into_inner().ok())
+        .unwrap_or_default();
+    let line = String::from_utf8_lossy(&line);
+    line.strip_suffix('\n').unwrap_or(&line).to_string()
+}
+
+/// The record as a line of CSV, for logging and the load_rejections table
+fn raw_record(record: &csv::StringRecord) -> String {
+    let mut csv = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+
+    // writing to a Vec can't fail
+    let line = csv
+        .write_record(record)
+        .ok()
+        .and_then(|()| csv.into_inner().ok())
+        .unwrap_or_default();
+    let line = String::from_utf8_lossy(&line);
+    line.strip_suffix('\n').unwrap_or(&line).to_string()
+}
 
-    let replace_spaces: &Regex =
-        REPLACE_SPACES.get_or_init(|| Regex::new(r"( +)").expect("bad regular expression"));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{create_tables, TempDir};
+
+    #[test]
+    fn test_toml() {
+        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+
+        let table_order = schemas.table_order().expect("no table order");
+        let position = |table| table_order.iter().position(|t| *t == table);
+        assert!(position("switrs_collisions") < position("switrs_parties"));
+        assert!(position("switrs_parties") < position("switrs_victims"));
+        assert!(position("ccrs_imports") < position("ccrs_crashes"));
+        assert!(position("ccrs_crashes") < position("ccrs_parties"));
+        assert_eq!(Some(table_order.len() - 1), position("cleanup"));
+        assert_eq!(
+            Some(&"beat_type"),
+            lookup_table_order(&schemas.lookup_tables, &schemas.lookup_schema)
+                .expect("no lookup table order")
+                .first()
+        );
+        assert_eq!(
+            schemas.tables["switrs_parties"].schema,
+            Path::new("schema/switrs_parties.sql")
+        );
+
+        let abbreviations = schemas
+            .road_abbreviations()
+            .expect("bad road abbreviations");
+        assert_eq!(
+            "SIXTH ST",
+            abbreviations.normalize_road("6TH STREET, 800 BLOCK").road()
+        );
+    }
+
+    #[test]
+    fn test_road_typos() {
+        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        let abbreviations = schemas
+            .road_abbreviations()
+            .expect("bad road abbreviations");
+        let typos = csv::Reader::from_path("berkeley-tables/BERKELEY_ROAD_TYPOS.csv")
+            .expect("no typos")
+            .into_records()
+            .map(|record| {
+                let record = record.expect("bad typo");
+                (record[0].to_string(), record[1].to_string())
+            })
+            .collect::<Vec<_>>();
+        let known = typos
+            .iter()
+            .map(|(_, correct_rd)| correct_rd.as_str())
+            .collect::<HashSet<_>>();
+
+        // the typos are looked up by the normalized roads, and before the known roads, so each one must be normalized
+        //   and can't be a known road itself, a known road is only listed as itself if no typo is corrected to it
+        for (normalized_rd, correct_rd) in &typos {
+            assert_eq!(
+                normalized_rd,
+                &abbreviations.normalize_road(normalized_rd).road(),
+                "the typo {normalized_rd} is never looked up"
+            );
+            if normalized_rd == correct_rd {
+                assert_eq!(
+                    1,
+                    typos.iter().filter(|(_, c)| c == correct_rd).count(),
+                    "{correct_rd} is already a known road"
+                );
+            } else {
+                assert!(
+                    !known.contains(normalized_rd.as_str()),
+                    "the known road {normalized_rd} is corrected to {correct_rd}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_toml_relative_paths() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let schemas = Schema::from_toml_file(&dir.join("Schemas.toml")).expect("toml is bad");
+
+        assert_eq!(dir.join("schema/pk_table.sql"), schemas.lookup_schema);
+        assert_eq!(
+            dir.join("lookup-tables/DAY_OF_WEEK.csv"),
+            schemas.lookup_tables["day_of_week"].data
+        );
+        assert_eq!(
+            dir.join("schema/switrs_parties.sql"),
+            schemas.tables["switrs_parties"].schema
+        );
+        assert!(matches!(
+            &schemas.tables["street_improvements"].data,
+            DataPath::Path(path) if path.is_file() && path.starts_with(dir)
+        ));
+        assert_eq!(
+            Ok(dir.join("berkeley-tables/CORRECTED_ROADS.csv")),
+            schemas
+                .corrected_roads_csv("switrs_corrected_roads")
+                .map_err(|e| e.to_string())
+        );
+        assert_eq!(
+            dir.join("old-switrs"),
+            schemas.resolve_path(Path::new("old-switrs"))
+        );
+    }
+
+    #[test]
+    fn test_schema_problems() {
+        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        assert_eq!(
+            Vec::<SchemaProblem>::new(),
+            schemas.problems(None, Path::new("tests/data/ccrs"))
+        );
+
+        let template_dir = TempDir::new("problems");
+        let template = template_dir.join("bad_template.sql");
+        fs::write(&template, "CREATE TABLE {table} ({columns});").expect("failed to write sql");
+        let toml = format!(
+            r#"
+            lookup-schema = "schema/pk_table.sql"
+
+            [corrected-roads]
+            switrs_corrected_roads = "berkeley-tables/CORRECTED_ROADS.csv"
+
+            [road-abbreviations]
+            street-types = "lookup-tables/DAY_OF_WEEK.csv"
+
+            [tables]
+            ccrs_crashes = {{ schema = "schema/ccrs_crashes.sql", type = "ccrs_data", path = "Crashes_(.csv" }}
+            ccrs_imports = {{ schema = "schema/ccrs_imports.sql", type = "empty" }}
+            ccrs_parties = {{ schema = "schema/ccrs_parties.sql", type = "ccrs_data", path = "Parties_([0-9]*).csv", parent = {{ table = "ccrs_crashes", key = "collision_id" }}, depends-on = ["no_such_table"] }}
+            ccrs_normalized_roads = {{ schema = "schema/ccrs_normalized_roads.sql", type = "empty", depends-on = ["bad_template"] }}
+            bad_template = {{ schema = "{template}", type = "empty", depends-on = ["ccrs_normalized_roads"] }}
+            load_rejections = {{ schema = "schema/load_rejections.sql", type = "raw_data", path = "VictimRecords.txt" }}
+
+            [lookup-tables]
+            ramp = {{ pk_type = "VARCHAR2(1)", data = "lookup-tables/PRIMARY_RAMP.csv" }}
+            day_of_week = {{ pk_type = "INTEGER", data = "lookup-tables/DAY_OF_WEEK.csv", schema = "schema/no_such_table.sql" }}
+            switrs_corrected_roads = {{ pk_type = "NULL", data = "berkeley-tables/CORRECTED_ROADS.csv", schema = "schema/switrs_corrected_roads.sql" }}
+            ccrs_corrected_roads = {{ pk_type = "NULL", data = "berkeley-tables/CCRS_CORRECTED_ROADS.csv", schema = "schema/ccrs_corrected_roads.sql" }}
+            "#,
+            template = template.display()
+        );
+        let schemas = Schema::from_toml(toml.as_bytes(), Path::new("Problems.toml"), Path::new(""))
+            .expect("toml is bad");
+        let problems = schemas
+            .problems(Some(Path::new("tests/data")), Path::new("tests/data/ccrs"))
+            .iter()
+            .map(|p| (p.section, p.table.clone()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                ("tables", "bad_template".into()),
+                ("tables", "ccrs_crashes".into()),
+                ("tables", "ccrs_parties".into()),
+                ("tables", "load_rejections".into()),
+                ("tables", "load_rejections".into()),
+                ("lookup-tables", "day_of_week".into()),
+                ("lookup-tables", "ramp".into()),
+                ("tables", "bad_template".into()),
+                ("corrected-roads", "ccrs_corrected_roads".into()),
+                ("road-abbreviations", "street-types".into()),
+            ],
+            problems
+        );
+        assert!(matches!(
+            schemas.validate(None, Path::new("tests/data/ccrs")),
+            Err(SchemaError::Invalid(problems)) if problems.len() == 9
+        ));
+        assert!(matches!(
+            schemas.table_order(),
+            Err(SchemaError::MissingTable { table, .. }) if table == "no_such_table"
+        ));
+    }
 
-    let caps = address_matcher.captures(road);
+    #[test]
+    fn test_foreign_key_violations() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+        connection
+            .create_table(
+                FOREIGN_KEY_VIOLATIONS_TABLE,
+                "",
+                Path::new("schema/foreign_key_violations.sql"),
+            )
+            .expect("failed to create table");
+        connection
+            .create_table("day_of_week", "CHAR(1)", Path::new("schema/pk_table.sql"))
+            .expect("failed to create table");
+        connection
+            .load_data("day_of_week", Path::new("lookup-tables/DAY_OF_WEEK.csv"))
+            .expect("failed to load data");
 
-    if let Some(caps) = caps {
-        let road = caps.name("street").map(|m| m.as_str()).unwrap_or(road);
-        let road = replace_spaces.replace_all(road, " ");
+        connection.defer_foreign_keys().expect("failed to defer");
+        connection
+            .execute_batch(
+                "CREATE TABLE crashes (id INTEGER PRIMARY KEY, day_of_week CHAR(1), FOREIGN KEY (day_of_week) REFERENCES day_of_week (id));
+                INSERT INTO crashes VALUES (1, '1'), (2, '9'), (3, '9'), (4, NULL);",
+            )
+            .expect("failed to insert crashes");
 
-        NormalizedRoad {
-            road,
-            address: caps
-                .name("address_pre")
-                .or_else(|| caps.name("address_post"))
-                .map(|s| s.as_str()),
-            block: caps.name("block").map(|m| m.as_str()),
-            direction: caps
-                .name("direction_post")
-                .or_else(|| caps.name("direction_pre"))
-                .map(|m| m.as_str()),
-        }
-    } else {
-        let road = replace_spaces.replace_all(road, " ");
+        let violations = connection
+            .check_foreign_keys(false)
+            .expect("failed to check foreign keys");
+        assert_eq!(
+            vec![ForeignKeyViolation {
+                table: String::from("crashes"),
+                column: String::from("day_of_week"),
+                parent: String::from("day_of_week"),
+                code: String::from("9"),
+                rows: 2,
+            }],
+            violations
+        );
+        let reported: (String, usize) = connection
+            .query_row(
+                "SELECT code, row_count FROM foreign_key_violations",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .expect("no violations reported");
+        assert_eq!((String::from("9"), 2), reported);
 
-        NormalizedRoad {
-            road,
-            address: None,
-            block: None,
-            direction: None,
-        }
+        assert!(matches!(
+            connection.check_foreign_keys(true),
+            Err(SchemaError::ForeignKeyViolations { codes: 1, rows: 2 })
+        ));
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn test_toml() {
-        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
-
-        assert_eq!(schemas.table_order[1], "switrs_parties");
+    fn test_topological_order() {
+        let dependencies = BTreeMap::from([
+            ("parties", BTreeSet::from([String::from("crashes")])),
+            ("crashes", BTreeSet::from([String::from("day_of_week")])),
+            ("victims", BTreeSet::from([String::from("parties")])),
+            ("build_manifest", BTreeSet::new()),
+        ]);
         assert_eq!(
-            schemas.tables["switrs_parties"].schema,
-            Path::new("schema/switrs_parties.sql")
+            vec!["build_manifest", "crashes", "parties", "victims"],
+            topological_order(&dependencies).expect("no order")
         );
+
+        let mut dependencies = dependencies;
+        dependencies.insert("crashes", BTreeSet::from([String::from("victims")]));
+        assert!(matches!(
+            topological_order(&dependencies),
+            Err(SchemaError::DependencyCycle(cycle)) if cycle == ["crashes", "victims", "parties", "crashes"]
+        ));
     }
 
     #[test]
@@ -634,7 +3527,7 @@ mod tests {
             .load_data("day_of_week", &table.data)
             .expect("failed to create table");
 
-        assert_eq!(7, count);
+        assert_eq!(7, count.rows);
     }
 
     #[test]
@@ -664,7 +3557,7 @@ mod tests {
             .load_data("pcf_violation_category", &table.data)
             .expect("failed to create table");
 
-        assert_eq!(26, count);
+        assert_eq!(26, count.rows);
     }
 
     #[test]
@@ -694,11 +3587,396 @@ mod tests {
             .load_data("primary_ramp", &table.data)
             .expect("failed to create table");
 
-        assert_eq!(12, count);
+        assert_eq!(12, count.rows);
+    }
+
+    #[test]
+    fn test_create_collisions() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+
+        // initialize all the lookup tables
+        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        connection
+            .connection()
+            .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)
+            .expect("failed to init lookup tables");
+
+        // create the normalized_roads schema
+        connection
+            .connection()
+            .create_table(
+                "switrs_normalized_roads",
+                "",
+                Path::new("schema/switrs_normalized_roads.sql"),
+            )
+            .expect("failed to create table");
+
+        connection
+            .connection()
+            .create_table(
+                "switrs_collisions",
+                "",
+                Path::new("schema/switrs_collisions.sql"),
+            )
+            .expect("failed to create table");
+
+        connection
+            .execute("SELECT * from switrs_collisions", [])
+            .expect("failed to execute query");
+
+        let count = connection
+            .connection()
+            .load_data("switrs_collisions", Path::new("tests/data/collisions.csv"))
+            .expect("failed to create table");
+
+        assert_eq!(40, count.rows);
+    }
+
+    #[test]
+    fn test_create_parties() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+
+        // initialize all the lookup tables
+        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        connection
+            .connection()
+            .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)
+            .expect("failed to init lookup tables");
+
+        // create the normalized_roads schema
+        connection
+            .connection()
+            .create_table(
+                "switrs_normalized_roads",
+                "",
+                Path::new("schema/switrs_normalized_roads.sql"),
+            )
+            .expect("failed to create table");
+
+        // load test data into the collisions table
+        connection
+            .connection()
+            .create_table(
+                "switrs_collisions",
+                "",
+                Path::new("schema/switrs_collisions.sql"),
+            )
+            .expect("failed to create table");
+        connection
+            .connection()
+            .load_data("switrs_collisions", Path::new("tests/data/collisions.csv"))
+            .expect("failed to create table");
+
+        // parties
+        connection
+            .connection()
+            .create_table("switrs_parties", "", Path::new("schema/switrs_parties.sql"))
+            .expect("failed to create table");
+
+        connection
+            .execute("SELECT * from switrs_parties", [])
+            .expect("failed to execute query");
+
+        let count = connection
+            .connection()
+            .load_data("switrs_parties", Path::new("tests/data/parties.csv"))
+            .expect("failed to create table");
+
+        assert_eq!(80, count.rows);
+    }
+
+    #[test]
+    fn test_create_victims() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+
+        // initialize all the lookup tables
+        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        connection
+            .connection()
+            .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)
+            .expect("failed to init lookup tables");
+
+        // create the normalized_roads schema
+        connection
+            .connection()
+            .create_table(
+                "switrs_normalized_roads",
+                "",
+                Path::new("schema/switrs_normalized_roads.sql"),
+            )
+            .expect("failed to create table");
+
+        // load test data into the collisions table
+        connection
+            .connection()
+            .create_table(
+                "switrs_collisions",
+                "",
+                Path::new("schema/switrs_collisions.sql"),
+            )
+            .expect("failed to create table");
+        connection
+            .connection()
+            .load_data("switrs_collisions", Path::new("tests/data/collisions.csv"))
+            .expect("failed to create table");
+
+        // load parties
+        connection
+            .connection()
+            .create_table("switrs_parties", "", Path::new("schema/switrs_parties.sql"))
+            .expect("failed to create table");
+        connection
+            .connection()
+            .load_data("switrs_parties", Path::new("tests/data/parties.csv"))
+            .expect("failed to create table");
+
+        // test victims
+        connection
+            .connection()
+            .create_table("switrs_victims", "", Path::new("schema/switrs_victims.sql"))
+            .expect("failed to create table");
+
+        connection
+            .execute("SELECT * from switrs_victims", [])
+            .expect("failed to execute query");
+
+        let count = connection
+            .connection()
+            .load_data("switrs_victims", Path::new("tests/data/victims.csv"))
+            .expect("failed to create table");
+
+        assert_eq!(39, count.rows);
+    }
+
+    #[test]
+    fn test_create_ccrs_parties() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+
+        // initialize all the lookup tables
+        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        connection
+            .connection()
+            .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)
+            .expect("failed to init lookup tables");
+
+        connection
+            .connection()
+            .create_table("ccrs_parties", "", Path::new("schema/ccrs_parties.sql"))
+            .expect("failed to create table");
+
+        let count = connection
+            .connection()
+            .load_data(
+                "ccrs_parties",
+                Path::new("tests/data/ccrs/Parties_2024.csv"),
+            )
+            .expect("failed to create table");
+        assert_eq!(9, count.rows);
+
+        let (party_type, movement, sobriety_1, sobriety_2): (String, String, String, String) =
+            connection
+                .query_row(
+                    "SELECT party_type_name, movement_prec_coll_name, sobriety_drug_physical_1_name, sobriety_drug_physical_2_name
+                    FROM ccrs_parties_view WHERE party_id = 5801003",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+                )
+                .expect("failed to query parties view");
+        assert_eq!("Driver", party_type);
+        assert_eq!("Making Right Turn", movement);
+        assert_eq!("Impairment Unknown", sobriety_1);
+        assert_eq!("Sleepy/Fatigued", sobriety_2);
+    }
+
+    #[test]
+    fn test_create_ccrs_injured_witness_passengers() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+
+        // initialize all the lookup tables
+        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        connection
+            .connection()
+            .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)
+            .expect("failed to init lookup tables");
+
+        connection
+            .connection()
+            .create_table(
+                "ccrs_injured_witness_passengers",
+                "",
+                Path::new("schema/ccrs_injured_witness_passengers.sql"),
+            )
+            .expect("failed to create table");
+
+        let count = connection
+            .connection()
+            .load_data(
+                "ccrs_injured_witness_passengers",
+                Path::new("tests/data/ccrs/InjuredWitnessPassengers_2024.csv"),
+            )
+            .expect("failed to create table");
+        assert_eq!(6, count.rows);
+
+        let (extent_of_injury, seat_position): (String, String) = connection
+            .query_row(
+                "SELECT extent_of_injury_name, seat_position_name
+                FROM ccrs_injured_witness_passengers_view WHERE injured_wit_pass_id = 6901004",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .expect("failed to query injured witness passengers view");
+        assert_eq!("Possible Injury", extent_of_injury);
+        assert_eq!("Passenger (Rear Left)", seat_position);
+    }
+
+    #[test]
+    fn test_normalize_ccrs_roads() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+        create_tables(
+            &connection,
+            &[
+                ("ccrs_crashes", Some("tests/data/ccrs/Crashes_2024.csv")),
+                ("ccrs_normalized_roads", None),
+            ],
+        );
+
+        let count = connection
+            .normalize_roads(&RoadTables::CCRS, &RoadAbbreviations::default())
+            .expect("failed to normalize roads");
+        assert_eq!(5, count);
+
+        let (primary_rd, block, secondary_rd): (String, String, String) = connection
+            .query_row(
+                "SELECT primary_rd, primary_rd_block, secondary_rd FROM ccrs_normalized_roads WHERE collision_id = 2701002",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .expect("failed to query normalized roads");
+        assert_eq!("CEDAR ST", primary_rd);
+        assert_eq!("2200", block);
+        assert_eq!("OXFORD ST", secondary_rd);
+
+        let secondary_rd: Option<String> = connection
+            .query_row(
+                "SELECT secondary_rd FROM ccrs_normalized_roads WHERE collision_id = 2701005",
+                [],
+                |row| row.get(0),
+            )
+            .expect("failed to query normalized roads");
+        assert_eq!(None, secondary_rd);
+    }
+
+    #[test]
+    fn test_fixup_ccrs_roads() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+        create_tables(
+            &connection,
+            &[
+                ("ccrs_crashes", Some("tests/data/ccrs/Crashes_2024.csv")),
+                ("ccrs_normalized_roads", None),
+                (
+                    "berkeley_road_typos",
+                    Some("berkeley-tables/BERKELEY_ROAD_TYPOS.csv"),
+                ),
+                (
+                    "ccrs_corrected_roads",
+                    Some("berkeley-tables/CCRS_CORRECTED_ROADS.csv"),
+                ),
+            ],
+        );
+
+        // the corrections are written outside of the source tree
+        let corrections_dir = TempDir::new("corrections");
+        let mut schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        schemas.set_corrected_roads_dir(corrections_dir.path());
+        let corrected_csv = corrections_dir.join("CCRS_CORRECTED_ROADS.csv");
+
+        connection
+            .fixup_roads(&RoadTables::CCRS, &schemas)
+            .expect("failed to fixup roads");
+        let corrections = fs::read_to_string(&corrected_csv).expect("corrections not written");
+        assert_eq!(6, corrections.lines().count());
+        assert!(corrections.contains("2701002,\"CEDAR ST\",\"OXFORD ST\"\n"));
+
+        let count: usize = connection
+            .query_row("SELECT count(*) FROM ccrs_corrected_roads", [], |row| {
+                row.get(0)
+            })
+            .expect("failed to count");
+        assert_eq!(5, count);
+
+        // checking against the written corrections finds no changes, and fails once one is removed
+        schemas.set_corrections(Corrections::Check);
+        schemas
+            .lookup_tables
+            .get_mut("ccrs_corrected_roads")
+            .expect("no corrected roads")
+            .data = corrected_csv.clone();
+        connection
+            .fixup_roads(&RoadTables::CCRS, &schemas)
+            .expect("corrections changed");
+
+        fs::write(&corrected_csv, corrections.replace("2701002,", "2701999,"))
+            .expect("failed to write corrections");
+        assert!(matches!(
+            connection.fixup_roads(&RoadTables::CCRS, &schemas),
+            Err(SchemaError::CorrectionsOutOfDate { .. })
+        ));
+        assert_eq!(
+            corrections.replace("2701002,", "2701999,"),
+            fs::read_to_string(&corrected_csv).expect("corrections removed")
+        );
+    }
+
+    #[test]
+    fn test_suggest_roads() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+        create_tables(
+            &connection,
+            &[(
+                "berkeley_road_typos",
+                Some("berkeley-tables/BERKELEY_ROAD_TYPOS.csv"),
+            )],
+        );
+
+        let suggestions_dir = TempDir::new("suggestions");
+        let suggestions_csv = suggestions_dir.join("ROAD_SUGGESTIONS.csv");
+        let mut schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+
+        // the unknown roads are suggested once, with the most confident known road first
+        let unknown = |case_id: &str| UnknownRoad {
+            case_id: case_id.to_string(),
+            column: "primary_rd",
+            original: String::from("SHATUCK AVENUE"),
+            normalized: String::from("SHATUCK AVE"),
+        };
+        let unknown_roads = [unknown("2701998"), unknown("2701999")];
+
+        // nothing is written unless a file is set for the suggestions
+        connection
+            .suggest_roads(&unknown_roads, &schemas)
+            .expect("failed to suggest roads");
+        assert!(!suggestions_csv.exists());
+
+        schemas.set_road_suggestions(&suggestions_csv);
+        connection
+            .suggest_roads(&unknown_roads, &schemas)
+            .expect("failed to suggest roads");
+        let suggestions = fs::read_to_string(&suggestions_csv).expect("suggestions not written");
+        let mut suggestions = suggestions.lines();
+        assert_eq!(
+            Some("normalized_rd,correct_rd,confidence,rank,crashes,original_rd"),
+            suggestions.next()
+        );
+        assert!(suggestions
+            .next()
+            .is_some_and(|s| s.starts_with("SHATUCK AVE,SHATTUCK AVE,")
+                && s.ends_with(",1,2,SHATUCK AVENUE")));
+        assert_eq!(2, suggestions.count());
     }
 
     #[test]
-    fn test_create_collisions() {
+    fn test_crashes_view() {
         let connection = Connection::open_in_memory().expect("failed to open in memory DB");
 
         // initialize all the lookup tables
@@ -708,39 +3986,97 @@ mod tests {
             .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)
             .expect("failed to init lookup tables");
 
-        // create the normalized_roads schema
+        // legacy switrs data, and ccrs data, with one crash also in the switrs data
+        create_tables(
+            &connection,
+            &[
+                ("switrs_collisions", Some("tests/data/collisions.csv")),
+                ("ccrs_crashes", None),
+                ("ccrs_parties", None),
+            ],
+        );
+        for data in [
+            "tests/data/ccrs/Crashes_2023.csv",
+            "tests/data/ccrs/Crashes_2024.csv",
+        ] {
+            connection
+                .connection()
+                .load_data("ccrs_crashes", Path::new(data))
+                .expect("failed to load crashes");
+        }
         connection
-            .connection()
-            .create_table(
-                "switrs_normalized_roads",
-                "",
-                Path::new("schema/switrs_normalized_roads.sql"),
+            .execute(
+                "INSERT INTO ccrs_corrected_roads VALUES (2690001, 'UNIVERSITY AVE', 'SACRAMENTO ST')",
+                [],
             )
-            .expect("failed to create table");
+            .expect("failed to insert correction");
 
         connection
             .connection()
-            .create_table(
-                "switrs_collisions",
-                "",
-                Path::new("schema/switrs_collisions.sql"),
+            .create_table("crashes", "", Path::new("schema/crashes.sql"))
+            .expect("failed to create view");
+
+        let count = |source: &str| -> usize {
+            connection
+                .query_row(
+                    "SELECT count(*) FROM crashes_view WHERE source = ?",
+                    [source],
+                    |row| row.get(0),
+                )
+                .expect("failed to count crashes")
+        };
+
+        assert_eq!(6, count("ccrs"));
+        assert_eq!(39, count("switrs"));
+
+        let (severity, bicycle_accident): (String, Option<String>) = connection
+            .query_row(
+                "SELECT severity, bicycle_accident FROM crashes_view WHERE crash_id = '2701001'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
             )
-            .expect("failed to create table");
+            .expect("failed to query crash");
+        assert_eq!("Injury", severity);
+        assert_eq!(Some("Y"), bicycle_accident.as_deref());
 
+        // the report numbers are only the same crash when they are from the same agency
         connection
-            .execute("SELECT * from switrs_collisions", [])
-            .expect("failed to execute query");
-
-        let count = connection
-            .connection()
-            .load_data("switrs_collisions", Path::new("tests/data/collisions.csv"))
-            .expect("failed to create table");
+            .execute_batch(
+                "UPDATE switrs_collisions SET local_report_number = '23-0042' WHERE case_id = '9631228';
+                UPDATE ccrs_crashes SET report_number = '23-0042', ncic_code = '0109' WHERE collision_id = 2701001;",
+            )
+            .expect("failed to update report numbers");
+        assert_eq!(39, count("switrs"));
+        connection
+            .execute(
+                "UPDATE ccrs_crashes SET ncic_code = '0103' WHERE collision_id = 2701001",
+                [],
+            )
+            .expect("failed to update ncic code");
+        assert_eq!(38, count("switrs"));
 
-        assert_eq!(40, count);
+        // only the known severity codes are named
+        connection
+            .execute(
+                "UPDATE switrs_collisions SET collision_severity = NULL WHERE case_id = '9631232'",
+                [],
+            )
+            .expect("failed to update severity");
+        let severity = |crash_id: &str| -> Option<String> {
+            connection
+                .query_row(
+                    "SELECT severity FROM crashes_view WHERE crash_id = ?",
+                    [crash_id],
+                    |row| row.get(0),
+                )
+                .expect("failed to query crash")
+        };
+        assert_eq!(None, severity("9631232"));
+        assert_eq!(Some("Injury"), severity("9631272").as_deref());
     }
 
     #[test]
-    fn test_create_parties() {
+    fn test_jurisdiction() {
         let connection = Connection::open_in_memory().expect("failed to open in memory DB");
 
         // initialize all the lookup tables
@@ -750,210 +4086,757 @@ mod tests {
             .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)
             .expect("failed to init lookup tables");
 
-        // create the normalized_roads schema
-        connection
-            .connection()
-            .create_table(
-                "switrs_normalized_roads",
-                "",
-                Path::new("schema/switrs_normalized_roads.sql"),
-            )
-            .expect("failed to create table");
-
-        // load test data into the collisions table
-        connection
-            .connection()
-            .create_table(
+        let oakland = Jurisdiction::new(vec![String::from("oakland")], vec![]);
+        for (table, schema, data) in [
+            (
                 "switrs_collisions",
-                "",
-                Path::new("schema/switrs_collisions.sql"),
-            )
-            .expect("failed to create table");
-        connection
-            .connection()
-            .load_data("switrs_collisions", Path::new("tests/data/collisions.csv"))
-            .expect("failed to create table");
+                "schema/switrs_collisions.sql",
+                "tests/data/collisions.csv",
+            ),
+            (
+                "ccrs_crashes",
+                "schema/ccrs_crashes.sql",
+                "tests/data/ccrs/Crashes_2024.csv",
+            ),
+            (
+                "ccrs_parties",
+                "schema/ccrs_parties.sql",
+                "tests/data/ccrs/Parties_2024.csv",
+            ),
+            (
+                "ccrs_injured_witness_passengers",
+                "schema/ccrs_injured_witness_passengers.sql",
+                "tests/data/ccrs/InjuredWitnessPassengers_2024.csv",
+            ),
+        ] {
+            connection
+                .connection()
+                .create_table_for_jurisdiction(table, "", Path::new(schema), &oakland)
+                .expect("failed to create table");
+            connection
+                .connection()
+                .load_data(table, Path::new(data))
+                .expect("failed to load data");
+        }
 
-        // parties
         connection
             .connection()
-            .create_table("switrs_parties", "", Path::new("schema/switrs_parties.sql"))
-            .expect("failed to create table");
+            .create_table_for_jurisdiction("cleanup", "", Path::new("schema/cleanup.sql"), &oakland)
+            .expect("failed to cleanup");
 
-        connection
-            .execute("SELECT * from switrs_parties", [])
-            .expect("failed to execute query");
+        let count = |query: &str| -> usize {
+            connection
+                .query_row(query, [], |row| row.get(0))
+                .expect("failed to count")
+        };
 
-        let count = connection
-            .connection()
-            .load_data("switrs_parties", Path::new("tests/data/parties.csv"))
-            .expect("failed to create table");
+        // all of the switrs test data is in Berkeley
+        assert_eq!(0, count("SELECT count(*) FROM switrs_collisions_view"));
+        assert_eq!(40, count("SELECT count(*) FROM switrs_collisions"));
+        assert_eq!(1, count("SELECT count(*) FROM ccrs_crashes"));
+        assert_eq!(2, count("SELECT count(*) FROM ccrs_parties"));
+        assert_eq!(
+            2,
+            count("SELECT count(*) FROM ccrs_injured_witness_passengers")
+        );
 
-        assert_eq!(80, count);
+        // codes and counties
+        let params = Jurisdiction::new(vec![String::from("0102")], vec![String::from("Alameda")])
+            .template_params();
+        assert_eq!(
+            "cnty_city_loc IN (SELECT id FROM cnty_city_loc WHERE id IN ('0102') OR city COLLATE NOCASE IN () OR county COLLATE NOCASE IN ('Alameda'))",
+            params["switrs_jurisdiction"]
+        );
+        assert_eq!(
+            "1",
+            Jurisdiction::default().template_params()["ccrs_jurisdiction"]
+        );
     }
 
     #[test]
-    fn test_create_victims() {
+    fn test_incremental_ccrs_update() {
         let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+        let mut schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        schemas.set_jurisdiction(Jurisdiction::default());
+        let load = |ccrs_data_path: &Path| -> Vec<ImportedFile> {
+            connection
+                .load_ccrs_csvs(
+                    &schemas.tables["ccrs_crashes"],
+                    "ccrs_crashes",
+                    ccrs_data_path,
+                    "Crashes_([0-9]*).csv",
+                    &schemas,
+                )
+                .expect("failed to load ccrs crashes")
+        };
+        let crash = |collision_id: u32| -> (u32, String) {
+            connection
+                .query_row(
+                    "SELECT report_version, primary_road FROM ccrs_crashes WHERE collision_id = ?",
+                    [collision_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .expect("failed to query crash")
+        };
 
-        // initialize all the lookup tables
-        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
         connection
-            .connection()
+            .create_table(CCRS_IMPORTS_TABLE, "", Path::new("schema/ccrs_imports.sql"))
+            .expect("failed to create imports table");
+
+        // first import loads everything, the second one has nothing new
+        assert_eq!(2, load(Path::new("tests/data/ccrs")).len());
+        assert_eq!(0, load(Path::new("tests/data/ccrs")).len());
+
+        // a new monthly drop, with a newer version of one report and an older version of another
+        let update_dir = TempDir::new("incremental");
+        let mut crashes = fs::read_to_string("tests/data/ccrs/Crashes_2024.csv")
+            .expect("failed to read crashes")
+            .replacen("2701001,BPD24-0001,1,", "2701001,BPD24-0001,2,", 1)
+            .replacen("SHATTUCK AVE", "SHATTUCK AV", 1)
+            .replacen("2701002,BPD24-0002,1,", "2701002,BPD24-0002,0,", 1)
+            .replacen("CEDAR ST", "CEDAR", 1);
+        crashes.push_str("2701006,BPD24-0006,1,2024-09-01 12:00:00,Berkeley,1,C,REAR END,False,OTHER MOTOR VEHICLE,0,0,37.87,-122.27,ASHBY AVE,ADELINE ST,Daylight\n");
+        fs::write(update_dir.join("Crashes_2024.csv"), crashes).expect("failed to write crashes");
+
+        let imported = load(update_dir.path());
+        assert_eq!(1, imported.len());
+        assert_eq!(
+            LoadStats {
+                rows: 5,
+                skipped: 1,
+                errored: 0,
+                filtered: 0,
+                rejections: Vec::new(),
+            },
+            imported[0].stats
+        );
+
+        assert_eq!((2, String::from("SHATTUCK AV")), crash(2701001));
+        assert_eq!(1, crash(2701002).0);
+        assert_eq!(1, crash(2701006).0);
+
+        let count = |query: &str| -> usize {
+            connection
+                .query_row(query, [], |row| row.get(0))
+                .expect("failed to count")
+        };
+        assert_eq!(7, count("SELECT count(*) FROM ccrs_crashes"));
+        assert_eq!(
+            6,
+            count("SELECT row_count FROM ccrs_imports WHERE file_name = 'Crashes_2024.csv'")
+        );
+    }
+
+    #[test]
+    fn test_jurisdiction_filter() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+        let mut schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        schemas.set_jurisdiction(Jurisdiction::new(vec![String::from("oakland")], vec![]));
+        schemas.set_batch_size(2);
+        connection
             .init_lookup_tables(&schemas.lookup_tables, &schemas.lookup_schema)
             .expect("failed to init lookup tables");
+        connection
+            .create_table(CCRS_IMPORTS_TABLE, "", Path::new("schema/ccrs_imports.sql"))
+            .expect("failed to create imports table");
 
-        // create the normalized_roads schema
+        let load = |table_name: &str, regex: &str| -> Vec<ImportedFile> {
+            connection
+                .load_ccrs_csvs(
+                    &schemas.tables[table_name],
+                    table_name,
+                    Path::new("tests/data/ccrs"),
+                    regex,
+                    &schemas,
+                )
+                .expect("failed to load ccrs data")
+        };
+
+        // only the one crash in Oakland, and its parties are loaded
+        let crashes = load("ccrs_crashes", "Crashes_(2024).csv");
+        assert_eq!(1, crashes[0].stats.rows);
+        assert_eq!(4, crashes[0].stats.filtered);
+
+        let parties = load("ccrs_parties", "Parties_([0-9]*).csv");
+        assert_eq!(2, parties[0].stats.rows);
+        assert_eq!(7, parties[0].stats.filtered);
+
+        let count: usize = connection
+            .query_row("SELECT count(*) FROM ccrs_parties", [], |row| row.get(0))
+            .expect("failed to count");
+        assert_eq!(2, count);
+    }
+
+    #[test]
+    fn test_ccrs_child_report_versions() {
+        let mut schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        schemas.set_jurisdiction(Jurisdiction::default());
+
+        // a newer version of crash 2701001, with one party, in an older release
+        let data_dir = TempDir::new("child-versions");
+        for file in ["Crashes_2024.csv", "Parties_2024.csv"] {
+            fs::copy(format!("tests/data/ccrs/{file}"), data_dir.join(file))
+                .expect("failed to copy data");
+        }
+        let older_release = |file: &str, line: &str, replacement: &str| {
+            let data = fs::read_to_string(format!("tests/data/ccrs/{file}_2024.csv"))
+                .expect("failed to read data");
+            let mut lines = data.lines();
+            let header = lines.next().expect("no header");
+            let row = lines
+                .find(|l| l.starts_with(line))
+                .expect("no row")
+                .replacen(line, replacement, 1);
+            fs::write(
+                data_dir.join(format!("{file}_2023.csv")),
+                format!("{header}\n{row}\n"),
+            )
+            .expect("failed to write data");
+        };
+        older_release("Crashes", "2701001,BPD24-0001,1,", "2701001,BPD24-0001,2,");
+        older_release("Parties", "5801001,", "5801010,");
+
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
         connection
-            .connection()
-            .create_table(
-                "switrs_normalized_roads",
-                "",
-                Path::new("schema/switrs_normalized_roads.sql"),
+            .create_table(CCRS_IMPORTS_TABLE, "", Path::new("schema/ccrs_imports.sql"))
+            .expect("failed to create imports table");
+        let load = |table_name: &str, regex: &str| -> Vec<ImportedFile> {
+            connection
+                .load_ccrs_csvs(
+                    &schemas.tables[table_name],
+                    table_name,
+                    data_dir.path(),
+                    regex,
+                    &schemas,
+                )
+                .expect("failed to load ccrs data")
+        };
+        let parties = |collision_id: u32| -> Vec<u32> {
+            let mut stmt = connection
+                .prepare(
+                    "SELECT party_id FROM ccrs_parties WHERE collision_id = ? ORDER BY party_id",
+                )
+                .expect("bad query");
+            stmt.query_map([collision_id], |row| row.get(0))
+                .expect("failed to query parties")
+                .collect::<Result<_, _>>()
+                .expect("bad party")
+        };
+
+        // the parties of the older version, from the newer release, are skipped
+        load("ccrs_crashes", "Crashes_([0-9]*).csv");
+        let imported = load("ccrs_parties", "Parties_([0-9]*).csv");
+        assert_eq!(1, imported[0].stats.rows);
+        assert_eq!(7, imported[1].stats.rows);
+        assert_eq!(2, imported[1].stats.skipped);
+        assert_eq!(vec![5801010], parties(2701001));
+        assert_eq!(vec![5801003, 5801004], parties(2701002));
+
+        // a new version of crash 2701002 without parties deletes the parties of the old one
+        let crashes = fs::read_to_string("tests/data/ccrs/Crashes_2024.csv")
+            .expect("failed to read crashes")
+            .replacen("2701002,BPD24-0002,1,", "2701002,BPD24-0002,2,", 1);
+        let mut lines = crashes.lines();
+        let header = lines.next().expect("no header");
+        let row = lines.find(|l| l.starts_with("2701002,")).expect("no crash");
+        fs::write(
+            data_dir.join("Crashes_2025.csv"),
+            format!("{header}\n{row}\n"),
+        )
+        .expect("failed to write crashes");
+
+        assert_eq!(1, load("ccrs_crashes", "Crashes_([0-9]*).csv").len());
+        assert_eq!(2, load("ccrs_parties", "Parties_([0-9]*).csv").len());
+
+        assert_eq!(Vec::<u32>::new(), parties(2701002));
+        assert_eq!(vec![5801010], parties(2701001));
+        let count: usize = connection
+            .query_row("SELECT count(*) FROM ccrs_parties", [], |row| row.get(0))
+            .expect("failed to count");
+        assert_eq!(6, count);
+    }
+
+    #[test]
+    fn test_parallel_ccrs_load() {
+        let mut schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        schemas.set_jurisdiction(Jurisdiction::default());
+        schemas.set_batch_size(7);
+        schemas.set_parse_workers(2);
+
+        // more crashes than a parse chunk, with newer and older versions of the reports in earlier files
+        let data_dir = TempDir::new("parallel");
+        for year in ["2023", "2024"] {
+            fs::copy(
+                format!("tests/data/ccrs/Crashes_{year}.csv"),
+                data_dir.join(format!("Crashes_{year}.csv")),
             )
-            .expect("failed to create table");
+            .expect("failed to copy crashes");
+        }
+        let crashes = fs::read_to_string("tests/data/ccrs/Crashes_2024.csv")
+            .expect("failed to read crashes")
+            .replacen("2701001,BPD24-0001,1,", "2701001,BPD24-0001,2,", 1)
+            .replacen("2701002,BPD24-0002,1,", "2701002,BPD24-0002,0,", 1);
+        let mut crashes = crashes.lines().map(String::from).collect::<Vec<_>>();
+        let crash = crashes[1]
+            .split_once(',')
+            .expect("no collision id")
+            .1
+            .to_string();
+        crashes.extend((0..2500).map(|i| format!("{id},{crash}", id = 2800000 + i)));
+        fs::write(data_dir.join("Crashes_2025.csv"), crashes.join("\n") + "\n")
+            .expect("failed to write crashes");
+
+        let table = &schemas.tables["ccrs_crashes"];
+        let crashes = |connection: &Connection| -> Vec<(u32, u32, String)> {
+            let mut stmt = connection
+                .prepare("SELECT collision_id, report_version, primary_road FROM ccrs_crashes ORDER BY collision_id")
+                .expect("bad query");
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+                .expect("failed to query crashes")
+                .collect::<Result<_, _>>()
+                .expect("bad crash")
+        };
+
+        let parallel = Connection::open_in_memory().expect("failed to open in memory DB");
+        parallel
+            .create_table(CCRS_IMPORTS_TABLE, "", Path::new("schema/ccrs_imports.sql"))
+            .expect("failed to create imports table");
+        let imported = parallel
+            .load_ccrs_csvs(
+                table,
+                "ccrs_crashes",
+                data_dir.path(),
+                "Crashes_([0-9]*).csv",
+                &schemas,
+            )
+            .expect("failed to load ccrs crashes");
+
+        let sequential = Connection::open_in_memory().expect("failed to open in memory DB");
+        sequential
+            .load_from_csv(table, "ccrs_crashes", None, &schemas)
+            .expect("failed to create crashes");
+        for file in &imported {
+            let stats = sequential
+                .load_data_with_options(
+                    "ccrs_crashes",
+                    &file.path,
+                    &table.load_options(OnConflict::Upsert, schemas.batch_size(), None),
+                )
+                .expect("failed to load crashes");
+            assert_eq!(stats, file.stats);
+        }
+
+        let paths = imported
+            .iter()
+            .map(|f| f.path.file_name().expect("no file name"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec!["Crashes_2023.csv", "Crashes_2024.csv", "Crashes_2025.csv"],
+            paths
+        );
+        assert_eq!(2504, imported[2].stats.rows);
+        assert_eq!(1, imported[2].stats.skipped);
+
+        let loaded = crashes(&parallel);
+        assert_eq!(crashes(&sequential), loaded);
+        assert_eq!(2506, loaded.len());
+        assert_eq!(2, loaded[1].1);
+    }
+
+    #[test]
+    fn test_parse_in_order() {
+        use std::{
+            sync::atomic::{AtomicUsize, Ordering},
+            time::{Duration, Instant},
+        };
+
+        // the first files are parsed at the same time, each waits for the others to start
+        let parsing = AtomicUsize::new(0);
+        let (started, started_files) = mpsc::channel();
+        let parse = |file: usize| {
+            started.send(file).expect("the test hung up");
+            if file < 3 {
+                parsing.fetch_add(1, Ordering::SeqCst);
+                let timeout = Instant::now() + Duration::from_secs(10);
+                while parsing.load(Ordering::SeqCst) < 3 && Instant::now() < timeout {
+                    thread::sleep(Duration::from_millis(1));
+                }
+            }
+            let parsed_together = file >= 3 || parsing.load(Ordering::SeqCst) == 3;
+            (0..5).map(move |record| (file, record, parsed_together))
+        };
+
+        // and the later files are parsed while the earlier ones are inserted, in chunks
+        let mut inserted = Vec::new();
+        parse_in_order((0..8).collect(), 3, 2, parse, |index, records| {
+            if index == 1 {
+                let parsed_while_inserting = (0..4)
+                    .map(|_| started_files.recv_timeout(Duration::from_secs(10)))
+                    .any(|started| started == Ok(3));
+                assert!(
+                    parsed_while_inserting,
+                    "file 3 wasn't parsed while file 1 was inserted"
+                );
+            }
+            for (file, record, parsed_together) in records {
+                assert!(
+                    parsed_together,
+                    "the first files weren't parsed at the same time"
+                );
+                assert_eq!(index, file);
+                inserted.push((file, record));
+            }
+            Ok::<_, ()>(())
+        })
+        .expect("failed to insert");
+        let expected = (0..8)
+            .flat_map(|file| (0..5).map(move |record| (file, record)))
+            .collect::<Vec<_>>();
+        assert_eq!(expected, inserted);
+
+        // only a few chunks are parsed ahead of the inserts, not whole files
+        let parsed = AtomicUsize::new(0);
+        let mut inserted = 0;
+        parse_in_order(
+            (0..3).collect(),
+            1,
+            2,
+            |_: usize| {
+                (0..100).inspect(|_| {
+                    parsed.fetch_add(1, Ordering::SeqCst);
+                })
+            },
+            |_, records| {
+                thread::sleep(Duration::from_millis(50));
+                assert!(parsed.load(Ordering::SeqCst) <= inserted + 2 * (PARSED_CHUNKS + 1));
+                inserted += records.count();
+                Ok::<_, ()>(())
+            },
+        )
+        .expect("failed to insert");
+        assert_eq!(300, inserted);
+
+        // a failed insert stops the workers
+        let parsed = AtomicUsize::new(0);
+        let mut inserted = Vec::new();
+        let failed = parse_in_order(
+            (0..100).collect(),
+            2,
+            10,
+            |file: usize| {
+                parsed.fetch_add(1, Ordering::SeqCst);
+                iter::once(file)
+            },
+            |_, records| {
+                for file in records {
+                    inserted.push(file);
+                    if file == 1 {
+                        return Err(file);
+                    }
+                }
+                Ok(())
+            },
+        );
+        assert_eq!(Err(1), failed);
+        assert_eq!(vec![0, 1], inserted);
+        assert!(parsed.load(Ordering::SeqCst) <= 4);
+    }
+
+    #[test]
+    fn test_delete_ccrs_deleted_crashes() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+        create_tables(
+            &connection,
+            &[
+                (CCRS_DELETED_CRASHES_TABLE, None),
+                ("ccrs_crashes", Some("tests/data/ccrs/Crashes_2024.csv")),
+                ("ccrs_parties", Some("tests/data/ccrs/Parties_2024.csv")),
+                (
+                    "ccrs_injured_witness_passengers",
+                    Some("tests/data/ccrs/InjuredWitnessPassengers_2024.csv"),
+                ),
+            ],
+        );
+
+        // nothing is deleted in the test data
+        assert_eq!(
+            0,
+            connection
+                .delete_ccrs_deleted_crashes()
+                .expect("failed to delete")
+        );
 
-        // load test data into the collisions table
         connection
-            .connection()
-            .create_table(
-                "switrs_collisions",
-                "",
-                Path::new("schema/switrs_collisions.sql"),
+            .execute(
+                "UPDATE ccrs_crashes SET is_deleted = 'True' WHERE collision_id = 2701001",
+                [],
             )
-            .expect("failed to create table");
+            .expect("failed to mark crash deleted");
+        assert_eq!(
+            1,
+            connection
+                .delete_ccrs_deleted_crashes()
+                .expect("failed to delete")
+        );
+
+        let count = |query: &str| -> usize {
+            connection
+                .query_row(query, [], |row| row.get(0))
+                .expect("failed to count")
+        };
+        assert_eq!(4, count("SELECT count(*) FROM ccrs_crashes"));
+        assert_eq!(7, count("SELECT count(*) FROM ccrs_parties"));
+        assert_eq!(
+            5,
+            count("SELECT count(*) FROM ccrs_injured_witness_passengers")
+        );
+
+        // the deleted crash stays deleted when its older version is loaded again, but not a newer version
+        let reload = || {
+            connection
+                .load_data(
+                    "ccrs_crashes",
+                    Path::new("tests/data/ccrs/Crashes_2024.csv"),
+                )
+                .expect("failed to load data")
+        };
+        assert_eq!(1, reload().rows);
+        assert_eq!(
+            1,
+            connection
+                .delete_ccrs_deleted_crashes()
+                .expect("failed to delete")
+        );
+
+        reload();
         connection
-            .connection()
-            .load_data("switrs_collisions", Path::new("tests/data/collisions.csv"))
-            .expect("failed to create table");
+            .execute(
+                "UPDATE ccrs_crashes SET report_version = 2 WHERE collision_id = 2701001",
+                [],
+            )
+            .expect("failed to update crash");
+        assert_eq!(
+            0,
+            connection
+                .delete_ccrs_deleted_crashes()
+                .expect("failed to delete")
+        );
+        assert_eq!(5, count("SELECT count(*) FROM ccrs_crashes"));
+    }
 
-        // load parties
+    #[test]
+    fn test_build_manifest() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        assert_eq!(64, schemas.sha256.len());
+
+        create_tables(
+            &connection,
+            &[
+                ("build_manifest", None),
+                ("load_rejections", None),
+                ("ccrs_crashes", None),
+            ],
+        );
+
+        // the manifest records the file as it was loaded, even if it's rewritten before the end of the build
+        let manifest_dir = TempDir::new("manifest");
+        let path = manifest_dir.join("Crashes_2023.csv");
+        let data = fs::read("tests/data/ccrs/Crashes_2023.csv").expect("no test data");
+        fs::write(&path, &data).expect("failed to write csv");
+        let imported = connection
+            .import_data("ccrs_crashes", &path, &LoadOptions::default())
+            .expect("failed to load data");
+        fs::write(&path, "Collision Id\n").expect("failed to write csv");
         connection
-            .connection()
-            .create_table("switrs_parties", "", Path::new("schema/switrs_parties.sql"))
-            .expect("failed to create table");
+            .write_build_manifest(&schemas, "build", Path::new("tests/data/ccrs"), &[imported])
+            .expect("failed to write manifest");
+
+        let (tool_version, schema_sha256): (String, String) = connection
+            .query_row(
+                "SELECT tool_version, schema_sha256 FROM build_manifest WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .expect("failed to query manifest");
+        assert_eq!(env!("CARGO_PKG_VERSION"), tool_version);
+        assert_eq!(schemas.sha256, schema_sha256);
+
+        let (build_id, file_size, sha256, row_count): (i64, u64, String, usize) = connection
+            .query_row(
+                "SELECT build_id, file_size, sha256, row_count FROM build_manifest_files",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .expect("failed to query manifest files");
+        assert_eq!(1, build_id);
+        assert_eq!(data.len() as u64, file_size);
+        assert_eq!(format!("{:x}", Sha256::digest(&data)), sha256);
+        assert_eq!(1, row_count);
+
+        connection.check_build().expect("build is bad");
+
+        // an update that loads nothing new still checks the tables of the build
         connection
-            .connection()
-            .load_data("switrs_parties", Path::new("tests/data/parties.csv"))
-            .expect("failed to create table");
+            .write_build_manifest(&schemas, "update", Path::new("tests/data/ccrs"), &[])
+            .expect("failed to write manifest");
+        connection.check_build().expect("update is bad");
+        connection
+            .execute("DELETE FROM ccrs_crashes", [])
+            .expect("failed to delete crashes");
+        assert!(matches!(
+            connection.check_build(),
+            Err(SchemaError::BadBuild(_))
+        ));
+    }
 
-        // test victims
+    #[test]
+    fn test_load_rejections() {
+        let connection = Connection::open_in_memory().expect("failed to open in memory DB");
+        create_tables(
+            &connection,
+            &[("build_manifest", None), ("load_rejections", None)],
+        );
         connection
-            .connection()
-            .create_table("switrs_victims", "", Path::new("schema/switrs_victims.sql"))
+            .create_table("numbers", "INTEGER", Path::new("schema/pk_table.sql"))
             .expect("failed to create table");
 
-        connection
-            .execute("SELECT * from switrs_victims", [])
-            .expect("failed to execute query");
+        let rejections_dir = TempDir::new("rejections");
+        let path = rejections_dir.join("numbers.csv");
+        fs::write(&path, "id,name\n1,one\n2,two,extra\n1,uno\n3,three\n")
+            .expect("failed to write csv");
+        let imported = connection
+            .import_data("numbers", &path, &LoadOptions::default())
+            .expect("failed to load data");
+        let failed = connection.load_data_with_options(
+            "numbers",
+            &path,
+            &LoadOptions {
+                on_conflict: OnConflict::Fail,
+                ..LoadOptions::default()
+            },
+        );
 
-        let count = connection
-            .connection()
-            .load_data("switrs_victims", Path::new("tests/data/victims.csv"))
-            .expect("failed to create table");
+        let stats = &imported.stats;
+        assert!(matches!(failed, Err(SchemaError::Insert { line: 2, .. })));
+        assert_eq!(2, stats.rows);
+        assert_eq!(1, stats.skipped);
+        assert_eq!(1, stats.errored);
+        assert_eq!(
+            vec![
+                (Some(3), RejectionKind::Parse),
+                (Some(4), RejectionKind::Duplicate)
+            ],
+            stats
+                .rejections
+                .iter()
+                .map(|r| (r.line, r.kind))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some("1,uno"), stats.rejections[1].record.as_deref());
+
+        // only the first rejections of a file are kept for the load_rejections table
+        let duplicates = "1,uno\n".repeat(MAX_REJECTIONS + 10);
+        fs::write(&path, format!("id,name\n{duplicates}")).expect("failed to write csv");
+        let limited = connection
+            .load_data("numbers", &path)
+            .expect("failed to load data");
+        assert_eq!(MAX_REJECTIONS + 10, limited.skipped);
+        assert_eq!(MAX_REJECTIONS, limited.rejections.len());
+
+        let table = PrimaryTable {
+            schema: PathBuf::from("schema/pk_table.sql"),
+            data: DataPath::Empty,
+            error_budget: Some(0),
+            header_aliases: HashMap::new(),
+            jurisdiction_column: None,
+            parent: None,
+            depends_on: Vec::new(),
+        };
+        assert!(matches!(
+            table.check_error_budget("numbers", stats.errored),
+            Err(SchemaError::ErrorBudget {
+                errored: 1,
+                error_budget: 0,
+                ..
+            })
+        ));
 
-        assert_eq!(39, count);
+        let schemas = Schema::from_toml_file(Path::new("Schemas.toml")).expect("toml is bad");
+        connection
+            .write_build_manifest(&schemas, "build", Path::new("tests/data/ccrs"), &[imported])
+            .expect("failed to write manifest");
+
+        let count: usize = connection
+            .query_row(
+                "SELECT count(*) FROM load_rejections WHERE build_id = 1 AND table_name = 'numbers'",
+                [],
+                |row| row.get(0),
+            )
+            .expect("failed to count rejections");
+        assert_eq!(2, count);
     }
 
     #[test]
-    fn test_normalize_road() {
-        let test = |raw, road, address, block, direction| {
-            assert_eq!(
-                NormalizedRoad {
-                    road: Cow::Borrowed(road),
-                    address,
-                    block,
-                    direction,
-                },
-                normalize_road(raw)
-            )
-        };
-
-        test("GRANT", "GRANT", None, None, None);
-        test("1201 2ND ST", "2ND ST", Some("1201"), None, None);
-        test("WARD 1403", "WARD", Some("1403"), None, None);
-        test("RT 123", "RT 123", None, None, None);
-        test("RT 13", "RT 13", None, None, None);
-        test("RT 80 E", "RT 80", None, None, Some("E"));
-        test("RT1805", "RT1805", None, None, None);
-        test("SAN PABLO 1229", "SAN PABLO", Some("1229"), None, None);
-        test("6TH  ST", "6TH ST", None, None, None);
-        test("7 GAUSS WAY", "GAUSS WAY", Some("7"), None, None);
-        test("ASHBY AVE.", "ASHBY AVE", None, None, None);
-        test(
-            "EUCLID AVE (600 BLOCK)",
-            "EUCLID AVE",
-            None,
-            Some("600"),
-            None,
-        );
-        test("RT 80 E/B", "RT 80", None, None, Some("E/B"));
-        test("I-80 WB TO UNIVERSITY AVE", "I-80", None, None, Some("WB"));
-        test("I-80 E/B TO I-580 W/B", "I-80", None, None, Some("E/B"));
-        test(
-            "1313 NINTH STREET (PARKING LOT)",
-            "NINTH STREET",
-            Some("1313"),
-            None,
-            None,
-        );
-        test(
-            "85 EL CAMINO REAL RD",
-            "EL CAMINO REAL RD",
-            Some("85"),
-            None,
-            None,
-        );
-        test(
-            "8TH STREET, 1400 BLOCK",
-            "8TH STREET",
-            None,
-            Some("1400"),
-            None,
-        );
-        test(
-            "ADDISON ST. WESTBOUND, 1500 BLOCK",
-            "ADDISON ST",
-            None,
-            Some("1500"),
-            Some("WESTBOUND"),
-        );
-        test(
-            "CEDAR ST. (2200 BLOCK)",
-            "CEDAR ST",
-            None,
-            Some("2200"),
-            None,
-        );
-        test(
-            "CEDAR STREET, 1800 BLOCK",
-            "CEDAR STREET",
-            None,
-            Some("1800"),
-            None,
-        );
-        test(
-            "CHANNING WAY E/B  800 BLOCK",
-            "CHANNING WAY E",
-            None,
-            Some("800"),
-            None,
-        );
-        test(
-            "CAMPUS DR, 1400 BLOCK",
-            "CAMPUS DR",
-            None,
-            Some("1400"),
-            None,
-        );
-        test(
-            "CAMPUS DR., 1400 BLOCK",
-            "CAMPUS DR",
-            None,
-            Some("1400"),
-            None,
-        );
-        test("W COLUSA AV", "COLUSA AV", None, None, Some("W"));
-        test("EAST ASHBY AVE", "ASHBY AVE", None, None, Some("EAST"));
+    fn test_header_reconciliation() {
+        let columns = [
+            "party_id",
+            "collision_id",
+            "stated_age",
+            "sobriety_drug_physical_code1",
+        ]
+        .map(String::from);
+        let headers = csv::StringRecord::from(vec![
+            "Party Id",
+            "Collision ID",
+            "Age",
+            "Sobriety Drug Physical Code 1",
+        ]);
+
+        let reconciled = HeaderReconciliation::new(&headers, &columns, None);
+        assert_eq!(
+            vec![
+                Some(String::from("party_id")),
+                Some(String::from("collision_id")),
+                None,
+                None
+            ],
+            reconciled.fields
+        );
+        assert_eq!(
+            vec!["stated_age", "sobriety_drug_physical_code1"],
+            reconciled.missing
+        );
+        assert_eq!(
+            vec!["Age", "Sobriety Drug Physical Code 1"],
+            reconciled.extra
+        );
+        assert_eq!(
+            vec![(
+                String::from("Sobriety Drug Physical Code 1"),
+                String::from("sobriety_drug_physical_code1")
+            )],
+            reconciled.renamed
+        );
+        let err = reconciled
+            .check("ccrs_parties", Path::new("Parties_2025.csv"))
+            .expect_err("extra headers should fail");
+        assert!(matches!(err, SchemaError::Headers { .. }));
+        assert!(err.to_string().contains("header-aliases"));
+
+        let aliases = HashMap::from([
+            (String::from("Age"), String::from("stated_age")),
+            (
+                String::from("Sobriety Drug Physical Code 1"),
+                String::from("sobriety_drug_physical_code1"),
+            ),
+        ]);
+        let reconciled = HeaderReconciliation::new(&headers, &columns, Some(&aliases));
+        assert!(reconciled.extra.is_empty());
+        assert!(reconciled.missing.is_empty());
+        assert_eq!(Some(String::from("stated_age")), reconciled.fields[2]);
+        reconciled
+            .check("ccrs_parties", Path::new("Parties_2025.csv"))
+            .expect("all headers have columns");
     }
 }
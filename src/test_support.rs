@@ -0,0 +1,56 @@
+//! Helpers shared by the tests
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use rusqlite::Connection;
+
+use crate::schema::NewDB;
+
+/// A directory in the system temp dir for the files written by a test, removed with everything in it when dropped,
+///   even when the test panics
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Create the directory, named after the test and the process, so the tests don't share it
+    pub(crate) fn new(name: &str) -> Self {
+        let path =
+            env::temp_dir().join(format!("switrs-db-{name}-{pid}", pid = std::process::id()));
+        fs::create_dir_all(&path).expect("failed to create temp dir");
+        Self(path)
+    }
+
+    /// The path of the directory
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// The path of a file in the directory
+    pub(crate) fn join(&self, file: impl AsRef<Path>) -> PathBuf {
+        self.0.join(file)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_dir_all(&self.0) {
+            eprintln!("failed to remove {path}: {err}", path = self.0.display());
+        }
+    }
+}
+
+/// Create each table from its schema, schema/{table}.sql, and load the CSV file of the ones with data
+pub(crate) fn create_tables(connection: &Connection, tables: &[(&str, Option<&str>)]) {
+    for (table, data) in tables {
+        connection
+            .create_table(table, "", &Path::new("schema").join(format!("{table}.sql")))
+            .expect("failed to create table");
+        if let Some(data) = data {
+            connection
+                .load_data(table, Path::new(data))
+                .expect("failed to load data");
+        }
+    }
+}